@@ -0,0 +1,20 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate nom_bibtex;
+
+use nom_bibtex::cst::Document;
+use nom_bibtex::Bibtex;
+use std::str;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = str::from_utf8(data) {
+        if let Err(err) = Bibtex::parse(input) {
+            let _ = err.to_string();
+        }
+        let _ = Bibtex::parse_lenient(input);
+        if let Ok(document) = Document::parse(input) {
+            assert_eq!(document.to_string(), input);
+        }
+    }
+});
@@ -0,0 +1,368 @@
+//! Resolve the fields inherited through cross-references.
+//!
+//! An entry with a `crossref = {parent}` field inherits the fields of the
+//! `parent` entry which it doesn't define itself. Nested cross-references
+//! are resolved too, a missing or cyclic reference is reported as an error
+//! and the entry is then kept without the fields it should have inherited.
+//!
+//! With the *BibTeX* rules, every field is inherited as is.
+//!
+//! With the *biblatex* rules:
+//!
+//! - Some fields are renamed depending on the entry types, for example the
+//!   `title` of a `@proceedings` becomes the `booktitle` of an
+//!   `@inproceedings`, following the default inheritance setup of *biblatex*.
+//! - Fields such as `ids`, `crossref`, `xref`, `label` or `shorthand` are
+//!   never inherited.
+//! - An entry with a `xdata = {key1, key2}` field inherits all the fields of
+//!   the `@xdata` entries it lists, before the cross-reference. The `@xdata`
+//!   entries are only containers so they are not part of the result.
+
+use error::BibtexError;
+use model::{normalize_name, Bibliography};
+use std::collections::HashMap;
+
+/// The rules used to inherit the fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InheritanceRules {
+    /// Inherit every missing field of the `crossref` entry.
+    Bibtex,
+    /// Follow the default inheritance setup of *biblatex*, including the
+    /// `xdata` entries.
+    Biblatex,
+}
+
+/// Fields which are never inherited with the *biblatex* rules.
+const NOT_INHERITED: &[&str] = &[
+    "ids",
+    "crossref",
+    "xref",
+    "xdata",
+    "entryset",
+    "entrysubtype",
+    "execute",
+    "label",
+    "options",
+    "presort",
+    "related",
+    "relatedoptions",
+    "relatedstring",
+    "relatedtype",
+    "shorthand",
+    "shorthandintro",
+    "sortkey",
+];
+
+/// A renaming of the fields inherited from the `sources` entry types by the
+/// `targets` entry types. A field mapped to `None` is not inherited.
+struct Mapping {
+    sources: &'static [&'static str],
+    targets: &'static [&'static str],
+    fields: &'static [(&'static str, Option<&'static str>)],
+}
+
+const MAIN_TITLE_FIELDS: &[(&str, Option<&str>)] = &[
+    ("title", Some("maintitle")),
+    ("subtitle", Some("mainsubtitle")),
+    ("titleaddon", Some("maintitleaddon")),
+    ("shorttitle", None),
+    ("sorttitle", None),
+    ("indextitle", None),
+    ("indexsorttitle", None),
+];
+
+const BOOK_TITLE_FIELDS: &[(&str, Option<&str>)] = &[
+    ("title", Some("booktitle")),
+    ("subtitle", Some("booksubtitle")),
+    ("titleaddon", Some("booktitleaddon")),
+    ("shorttitle", None),
+    ("sorttitle", None),
+    ("indextitle", None),
+    ("indexsorttitle", None),
+];
+
+/// The default inheritance setup of *biblatex*.
+const BIBLATEX_MAPPINGS: &[Mapping] = &[
+    Mapping {
+        sources: &["mvbook", "book"],
+        targets: &["inbook", "bookinbook", "suppbook"],
+        fields: &[("author", Some("author")), ("author", Some("bookauthor"))],
+    },
+    Mapping {
+        sources: &["mvbook"],
+        targets: &["book", "inbook", "bookinbook", "suppbook"],
+        fields: MAIN_TITLE_FIELDS,
+    },
+    Mapping {
+        sources: &["mvcollection", "mvreference"],
+        targets: &[
+            "collection",
+            "reference",
+            "incollection",
+            "inreference",
+            "suppcollection",
+        ],
+        fields: MAIN_TITLE_FIELDS,
+    },
+    Mapping {
+        sources: &["mvproceedings"],
+        targets: &["proceedings", "inproceedings"],
+        fields: MAIN_TITLE_FIELDS,
+    },
+    Mapping {
+        sources: &["book"],
+        targets: &["inbook", "bookinbook", "suppbook"],
+        fields: BOOK_TITLE_FIELDS,
+    },
+    Mapping {
+        sources: &["collection", "reference"],
+        targets: &["incollection", "inreference", "suppcollection"],
+        fields: BOOK_TITLE_FIELDS,
+    },
+    Mapping {
+        sources: &["proceedings"],
+        targets: &["inproceedings"],
+        fields: BOOK_TITLE_FIELDS,
+    },
+    Mapping {
+        sources: &["periodical"],
+        targets: &["article", "suppperiodical"],
+        fields: &[
+            ("title", Some("journaltitle")),
+            ("subtitle", Some("journalsubtitle")),
+            ("titleaddon", Some("journaltitleaddon")),
+            ("shorttitle", None),
+            ("sorttitle", None),
+            ("indextitle", None),
+            ("indexsorttitle", None),
+        ],
+    },
+];
+
+/// Get the names under which a field of the `source` entry type is
+/// inherited by the `target` entry type.
+fn inherited_names(
+    rules: InheritanceRules,
+    source: &str,
+    target: &str,
+    field: &str,
+) -> Vec<String> {
+    let lowercase = field.to_lowercase();
+    if rules == InheritanceRules::Biblatex {
+        if NOT_INHERITED.contains(&lowercase.as_ref()) {
+            return vec![];
+        }
+        let (source, target) = (source.to_lowercase(), target.to_lowercase());
+        let renamings = BIBLATEX_MAPPINGS
+            .iter()
+            .filter(|m| {
+                m.sources.contains(&source.as_ref()) && m.targets.contains(&target.as_ref())
+            })
+            .flat_map(|m| m.fields.iter())
+            .filter(|&&(from, _)| from == lowercase)
+            .collect::<Vec<_>>();
+        if !renamings.is_empty() {
+            return renamings
+                .into_iter()
+                .filter_map(|&(_, to)| to.map(String::from))
+                .collect();
+        }
+    }
+    vec![field.into()]
+}
+
+/// Resolve the inheritance of all the bibliographies.
+///
+/// The targets are looked up ignoring the case of the citation keys,
+/// unless `case_sensitive_keys` is set.
+pub(crate) fn resolve<'a>(
+    bibliographies: &[Bibliography<'a>],
+    rules: InheritanceRules,
+    case_sensitive_keys: bool,
+) -> (Vec<Bibliography<'a>>, Vec<BibtexError>) {
+    let mut resolver = Resolver {
+        bibliographies,
+        rules,
+        case_sensitive_keys,
+        index: HashMap::new(),
+        resolved: vec![None; bibliographies.len()],
+        resolving: vec![],
+        errors: vec![],
+    };
+    for (i, bibliography) in bibliographies.iter().enumerate() {
+        let key = resolver.index_key(bibliography.citation_key());
+        resolver.index.entry(key).or_insert(i);
+    }
+    for i in 0..bibliographies.len() {
+        resolver.resolve(i);
+    }
+
+    let resolved = resolver
+        .resolved
+        .into_iter()
+        .map(|bibliography| bibliography.expect("Every bibliography is resolved"))
+        .filter(|bibliography| {
+            rules == InheritanceRules::Bibtex
+                || !bibliography.entry_type().eq_ignore_ascii_case("xdata")
+        })
+        .collect();
+    (resolved, resolver.errors)
+}
+
+struct Resolver<'a, 'b> {
+    bibliographies: &'b [Bibliography<'a>],
+    rules: InheritanceRules,
+    case_sensitive_keys: bool,
+    index: HashMap<String, usize>,
+    resolved: Vec<Option<Bibliography<'a>>>,
+    /// The bibliographies being resolved, to detect cycles.
+    resolving: Vec<usize>,
+    errors: Vec<BibtexError>,
+}
+
+impl<'a, 'b> Resolver<'a, 'b> {
+    fn resolve(&mut self, i: usize) {
+        if self.resolved[i].is_some() {
+            return;
+        }
+        self.resolving.push(i);
+
+        let mut bibliography = self.bibliographies[i].clone();
+        if self.rules == InheritanceRules::Biblatex {
+            let xdata = field(&bibliography, "xdata").unwrap_or_default();
+            for key in xdata
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+            {
+                if let Some(parent) = self.parent(&bibliography, key) {
+                    inherit(&mut bibliography, &parent, |field| {
+                        if NOT_INHERITED.contains(&field.to_lowercase().as_ref()) {
+                            vec![]
+                        } else {
+                            vec![field.into()]
+                        }
+                    });
+                }
+            }
+        }
+        if let Some(key) = field(&bibliography, "crossref") {
+            if let Some(parent) = self.parent(&bibliography, &key) {
+                let (rules, target) = (self.rules, self.bibliographies[i].entry_type());
+                inherit(&mut bibliography, &parent, |field| {
+                    inherited_names(rules, parent.entry_type(), target, field)
+                });
+            }
+        }
+
+        self.resolving.pop();
+        self.resolved[i] = Some(bibliography);
+    }
+
+    fn index_key(&self, key: &str) -> String {
+        if self.case_sensitive_keys {
+            key.into()
+        } else {
+            normalize_name(key)
+        }
+    }
+
+    /// Get the resolved bibliography referenced with `key` by `child`.
+    fn parent(&mut self, child: &Bibliography<'a>, key: &str) -> Option<Bibliography<'a>> {
+        let j = match self.index.get(&self.index_key(key)) {
+            Some(&j) => j,
+            None => {
+                self.errors.push(BibtexError::CrossrefNotFound(
+                    child.citation_key().into(),
+                    key.into(),
+                ));
+                return None;
+            }
+        };
+        if self.resolving.contains(&j) {
+            self.errors
+                .push(BibtexError::CyclicCrossref(child.citation_key().into()));
+            return None;
+        }
+        self.resolve(j);
+        self.resolved[j].clone()
+    }
+}
+
+/// Get the value of a field, ignoring the case of its name.
+fn field(bibliography: &Bibliography, name: &str) -> Option<String> {
+    bibliography.get(name).map(String::from)
+}
+
+/// Copy the fields of `parent` which `child` doesn't define, under the
+/// names given by `names`.
+fn inherit<'a, F>(child: &mut Bibliography<'a>, parent: &Bibliography<'a>, names: F)
+where
+    F: Fn(&str) -> Vec<String>,
+{
+    for (index, tag) in parent.tags().iter().enumerate() {
+        for name in names(&tag.0) {
+            if field(child, &name).is_none() {
+                child.inherit_tag(parent, index, &name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inherited_names() {
+        let names = |rules, source, target, field| inherited_names(rules, source, target, field);
+
+        assert_eq!(
+            names(
+                InheritanceRules::Bibtex,
+                "proceedings",
+                "inproceedings",
+                "Title"
+            ),
+            vec!["Title"]
+        );
+        assert_eq!(
+            names(
+                InheritanceRules::Biblatex,
+                "Proceedings",
+                "inproceedings",
+                "title"
+            ),
+            vec!["booktitle"]
+        );
+        assert_eq!(
+            names(
+                InheritanceRules::Biblatex,
+                "proceedings",
+                "inproceedings",
+                "shorttitle"
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names(InheritanceRules::Biblatex, "book", "inbook", "author"),
+            vec!["author", "bookauthor"]
+        );
+        assert_eq!(
+            names(InheritanceRules::Biblatex, "mvbook", "book", "title"),
+            vec!["maintitle"]
+        );
+        assert_eq!(
+            names(InheritanceRules::Biblatex, "periodical", "article", "title"),
+            vec!["journaltitle"]
+        );
+        assert_eq!(
+            names(InheritanceRules::Biblatex, "book", "misc", "title"),
+            vec!["title"]
+        );
+        assert_eq!(
+            names(InheritanceRules::Biblatex, "book", "inbook", "ids"),
+            Vec::<String>::new()
+        );
+    }
+}
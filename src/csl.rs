@@ -0,0 +1,557 @@
+//! Convert the bibliographies to and from CSL-JSON, the format of the
+//! citation processors and of Zotero, enabled with the `csl` feature.
+//!
+//! The values are decoded from *LaTeX* to Unicode when exported, and the
+//! special characters are escaped when imported, see the `latex` module.
+//!
+//! ## Mapping
+//!
+//! | BibTeX                                  | CSL-JSON                         |
+//! |-----------------------------------------|----------------------------------|
+//! | citation key                            | `id`                             |
+//! | `@article`                              | `article-journal`                |
+//! | `@book`, `@proceedings`, `@collection`  | `book`                           |
+//! | `@booklet`                              | `pamphlet`                       |
+//! | `@inbook`, `@incollection`              | `chapter`                        |
+//! | `@inproceedings`, `@conference`         | `paper-conference`               |
+//! | `@manual`, `@techreport`, `@report`     | `report`                         |
+//! | `@mastersthesis`, `@phdthesis`          | `thesis`                         |
+//! | `@unpublished`                          | `manuscript`                     |
+//! | `@online`                               | `webpage`                        |
+//! | other types                             | `document`                       |
+//! | `author`, `editor`, `translator`        | `author`, `editor`, `translator` |
+//! | `year`, `month` or `date`               | `issued`                         |
+//! | `urldate`                               | `accessed`                       |
+//! | `title`                                 | `title`                          |
+//! | `journal`, `booktitle`                  | `container-title`                |
+//! | `series`                                | `collection-title`               |
+//! | `publisher`, `school`, `institution`    | `publisher`                      |
+//! | `address`, `location`                   | `publisher-place`                |
+//! | `number` of an `@article`               | `issue`                          |
+//! | `number`                                | `number`                         |
+//! | `pages` (`12--34`)                      | `page` (`12-34`)                 |
+//! | `chapter`                               | `chapter-number`                 |
+//! | `edition`, `volume`, `note`, `abstract` | the same names                   |
+//! | `doi`, `url`, `isbn`, `issn`            | `DOI`, `URL`, `ISBN`, `ISSN`     |
+//! | `keywords`, `language`, `type`          | `keyword`, `language`, `genre`   |
+//!
+//! The `doi` and `url` fields are kept verbatim. The names are split in
+//! `family`, `given`, `non-dropping-particle` and `suffix`, a name enclosed
+//! in braces such as `{Barnes and Noble}` is a `literal` one. The other
+//! fields are not converted.
+//!
+//! The other way around, the CSL types are converted to the matching
+//! *BibTeX* entry types (`chapter` to `@incollection`, `webpage` and the
+//! unknown types to `@misc`), a `thesis` is a `@mastersthesis` if its
+//! `genre` mentions a master. The `container-title` is the `journal` of an
+//! `@article` and the `booktitle` of the other types, the `publisher` is
+//! the `school` of a thesis and the `institution` of a report.
+//!
+//! ## Example
+//!
+//! ```
+//! use nom_bibtex::csl::{from_csl_json, to_csl};
+//! use nom_bibtex::Bibtex;
+//!
+//! let bibtex = Bibtex::parse("@article{key, author = {G{\\\"o}del, Kurt}, year = 1931, pages = {173--198}}").unwrap();
+//! let item = to_csl(&bibtex.bibliographies()[0]);
+//! assert_eq!(item["type"], "article-journal");
+//! assert_eq!(item["author"][0]["family"], "Gödel");
+//! assert_eq!(item["issued"]["date-parts"][0][0], 1931);
+//! assert_eq!(item["page"], "173-198");
+//!
+//! let bibliographies = from_csl_json(&item.to_string()).unwrap();
+//! assert_eq!(bibliographies[0].get("pages"), Some("173--198"));
+//! assert_eq!(bibliographies[0].get("author"), Some("Gödel, Kurt"));
+//! ```
+
+use latex::{decode, encode, Encoding};
+use model::{Bibliography, Delimiters};
+use names::Name;
+use parser;
+use serde_json::{self, Map, Value};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum CslError {
+        Json (err: serde_json::Error) {
+            description("Invalid JSON.")
+            display("Invalid JSON.: {}", err)
+            from()
+        }
+        InvalidItem (reason: String) {
+            description("Invalid CSL-JSON item.")
+            display("Invalid CSL-JSON item.: {}", reason)
+        }
+    }
+}
+
+/// The entry types and the CSL types they are exported to.
+const TYPES: &[(&str, &str)] = &[
+    ("article", "article-journal"),
+    ("book", "book"),
+    ("proceedings", "book"),
+    ("collection", "book"),
+    ("booklet", "pamphlet"),
+    ("inbook", "chapter"),
+    ("incollection", "chapter"),
+    ("inproceedings", "paper-conference"),
+    ("conference", "paper-conference"),
+    ("manual", "report"),
+    ("techreport", "report"),
+    ("report", "report"),
+    ("mastersthesis", "thesis"),
+    ("phdthesis", "thesis"),
+    ("thesis", "thesis"),
+    ("unpublished", "manuscript"),
+    ("online", "webpage"),
+];
+
+/// The CSL types and the entry types they are imported to.
+const CSL_TYPES: &[(&str, &str)] = &[
+    ("article", "article"),
+    ("article-journal", "article"),
+    ("article-magazine", "article"),
+    ("article-newspaper", "article"),
+    ("book", "book"),
+    ("pamphlet", "booklet"),
+    ("chapter", "incollection"),
+    ("paper-conference", "inproceedings"),
+    ("report", "techreport"),
+    ("thesis", "phdthesis"),
+    ("manuscript", "unpublished"),
+];
+
+/// The fields exported as text variables. The first field found is used
+/// when several fields have the same variable.
+const FIELDS: &[(&str, &str)] = &[
+    ("title", "title"),
+    ("journal", "container-title"),
+    ("journaltitle", "container-title"),
+    ("booktitle", "container-title"),
+    ("series", "collection-title"),
+    ("publisher", "publisher"),
+    ("school", "publisher"),
+    ("institution", "publisher"),
+    ("address", "publisher-place"),
+    ("location", "publisher-place"),
+    ("edition", "edition"),
+    ("volume", "volume"),
+    ("chapter", "chapter-number"),
+    ("doi", "DOI"),
+    ("url", "URL"),
+    ("isbn", "ISBN"),
+    ("issn", "ISSN"),
+    ("note", "note"),
+    ("abstract", "abstract"),
+    ("keywords", "keyword"),
+    ("language", "language"),
+    ("type", "genre"),
+];
+
+/// The text variables imported as fields, the fields depending on the entry
+/// type are handled separately.
+const CSL_FIELDS: &[(&str, &str)] = &[
+    ("title", "title"),
+    ("collection-title", "series"),
+    ("publisher-place", "address"),
+    ("edition", "edition"),
+    ("volume", "volume"),
+    ("issue", "number"),
+    ("number", "number"),
+    ("chapter-number", "chapter"),
+    ("DOI", "doi"),
+    ("URL", "url"),
+    ("ISBN", "isbn"),
+    ("ISSN", "issn"),
+    ("note", "note"),
+    ("abstract", "abstract"),
+    ("keyword", "keywords"),
+    ("language", "language"),
+];
+
+const NAME_FIELDS: &[&str] = &["author", "editor", "translator"];
+
+/// The fields which are not *LaTeX*, so neither decoded nor encoded.
+const VERBATIM_FIELDS: &[&str] = &["doi", "url"];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const MASTERS_THESIS: &str = "Master's thesis";
+
+/// Convert a bibliography to a CSL-JSON item.
+///
+/// The cross-references should be resolved beforehand to export the
+/// inherited fields.
+pub fn to_csl(bibliography: &Bibliography) -> Value {
+    let mut item = Map::new();
+    let entry_type = bibliography.entry_type().to_lowercase();
+    let csl_type = TYPES
+        .iter()
+        .find(|t| t.0 == entry_type)
+        .map_or("document", |t| t.1);
+    item.insert("id".into(), bibliography.citation_key().into());
+    item.insert("type".into(), csl_type.into());
+
+    for &field in NAME_FIELDS {
+        let names = bibliography
+            .names(field)
+            .iter()
+            .filter(|name| !name.is_others())
+            .map(csl_name)
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            item.insert(field.into(), Value::Array(names));
+        }
+    }
+
+    if let Some(issued) = issued(bibliography) {
+        item.insert("issued".into(), issued);
+    }
+    if let Some(accessed) = bibliography.get("urldate").and_then(date_parts) {
+        item.insert("accessed".into(), accessed);
+    }
+
+    for &(field, variable) in FIELDS {
+        if let Some(value) = bibliography.get(field) {
+            if !item.contains_key(variable) {
+                let value = if VERBATIM_FIELDS.contains(&field) {
+                    value.to_string()
+                } else {
+                    decode(value).text
+                };
+                item.insert(variable.into(), value.into());
+            }
+        }
+    }
+    if entry_type == "mastersthesis" && !item.contains_key("genre") {
+        item.insert("genre".into(), MASTERS_THESIS.into());
+    }
+    if let Some(number) = bibliography.get("number") {
+        let variable = if entry_type == "article" {
+            "issue"
+        } else {
+            "number"
+        };
+        item.insert(variable.into(), decode(number).text.into());
+    }
+    if let Some(pages) = bibliography.get("pages") {
+        let pages = decode(pages).text.replace(['–', '—'], "-");
+        item.insert("page".into(), pages.into());
+    }
+    Value::Object(item)
+}
+
+/// Convert bibliographies to a CSL-JSON array.
+pub fn to_csl_json(bibliographies: &[Bibliography]) -> String {
+    let items = bibliographies.iter().map(to_csl).collect();
+    serde_json::to_string_pretty(&Value::Array(items)).expect("A JSON value can be serialized")
+}
+
+/// Convert a CSL-JSON item to a bibliography.
+pub fn from_csl(item: &Value) -> Result<Bibliography<'static>, CslError> {
+    let item = item
+        .as_object()
+        .ok_or_else(|| CslError::InvalidItem("an item must be an object".into()))?;
+    let id = item
+        .get("id")
+        .and_then(text)
+        .ok_or_else(|| CslError::InvalidItem("an item must have an `id`".into()))?;
+    if !parser::is_citation_key(&id, Delimiters::Braces) {
+        return Err(CslError::InvalidItem(format!(
+            "`{}` is not a valid citation key",
+            id
+        )));
+    }
+
+    let csl_type = item.get("type").and_then(Value::as_str).unwrap_or("");
+    let genre = item.get("genre").and_then(Value::as_str).unwrap_or("");
+    let entry_type = if csl_type == "thesis" && genre.to_lowercase().contains("master") {
+        "mastersthesis"
+    } else {
+        CSL_TYPES
+            .iter()
+            .find(|t| t.0 == csl_type)
+            .map_or("misc", |t| t.1)
+    };
+
+    let mut tags: Vec<(String, String)> = vec![];
+
+    for &field in NAME_FIELDS {
+        if let Some(names) = item.get(field).and_then(Value::as_array) {
+            let names = names.iter().filter_map(bibtex_name).collect::<Vec<_>>();
+            if !names.is_empty() {
+                push(&mut tags, field, names.join(" and "));
+            }
+        }
+    }
+
+    if let Some(issued) = item.get("issued") {
+        let parts = issued_parts(issued);
+        if let Some(year) = parts.first() {
+            push(&mut tags, "year", year.clone());
+        }
+        if let Some(month) = parts.get(1) {
+            push(&mut tags, "month", month.clone());
+        }
+    }
+    if let Some(accessed) = item.get("accessed").and_then(iso_date) {
+        push(&mut tags, "urldate", accessed);
+    }
+
+    let container = if entry_type == "article" {
+        "journal"
+    } else {
+        "booktitle"
+    };
+    let publisher = match entry_type {
+        "mastersthesis" | "phdthesis" => "school",
+        "techreport" => "institution",
+        _ => "publisher",
+    };
+    let fields = CSL_FIELDS.iter().cloned().chain(vec![
+        ("container-title", container),
+        ("publisher", publisher),
+    ]);
+    for (variable, field) in fields {
+        if let Some(value) = item.get(variable).and_then(text) {
+            let value = if VERBATIM_FIELDS.contains(&field) {
+                value
+            } else {
+                encode(&value, Encoding::Minimal)
+            };
+            push(&mut tags, field, value);
+        }
+    }
+    if let Some(pages) = item.get("page").and_then(text) {
+        let pages = encode(&pages, Encoding::Minimal).replace('-', "--");
+        push(&mut tags, "pages", pages.replace("----", "--"));
+    }
+    // The genre given to the master's theses on export is implied.
+    let implied = entry_type == "mastersthesis" && genre == MASTERS_THESIS;
+    if !genre.is_empty() && !implied {
+        push(&mut tags, "type", encode(genre, Encoding::Minimal));
+    }
+
+    Ok(Bibliography::new(entry_type, id, tags))
+}
+
+/// Add a tag unless a tag with the same name was already added.
+fn push(tags: &mut Vec<(String, String)>, name: &str, value: String) {
+    if !tags.iter().any(|tag| tag.0 == name) {
+        tags.push((name.into(), value));
+    }
+}
+
+/// Convert a CSL-JSON array, or a single item, to bibliographies.
+pub fn from_csl_json(json: &str) -> Result<Vec<Bibliography<'static>>, CslError> {
+    match serde_json::from_str(json)? {
+        Value::Array(items) => items.iter().map(from_csl).collect(),
+        item => Ok(vec![from_csl(&item)?]),
+    }
+}
+
+fn csl_name(name: &Name) -> Value {
+    let mut csl = Map::new();
+    let is_literal = name.first.is_empty()
+        && name.von.is_empty()
+        && name.jr.is_empty()
+        && name.last.starts_with('{')
+        && name.last.ends_with('}');
+    if is_literal {
+        csl.insert("literal".into(), decode(&name.last).text.into());
+        return Value::Object(csl);
+    }
+    let parts = [
+        ("family", &name.last),
+        ("given", &name.first),
+        ("non-dropping-particle", &name.von),
+        ("suffix", &name.jr),
+    ];
+    for &(part, value) in &parts {
+        if !value.is_empty() {
+            csl.insert(part.into(), decode(value).text.into());
+        }
+    }
+    Value::Object(csl)
+}
+
+fn bibtex_name(csl: &Value) -> Option<String> {
+    let part = |name: &str| {
+        csl.get(name)
+            .and_then(Value::as_str)
+            .map_or_else(String::new, |part| encode(part, Encoding::Minimal))
+    };
+    if let Some(literal) = csl.get("literal").and_then(Value::as_str) {
+        return Some(format!("{{{}}}", encode(literal, Encoding::Minimal)));
+    }
+    let von = [part("dropping-particle"), part("non-dropping-particle")]
+        .iter()
+        .filter(|particle| !particle.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name = Name {
+        first: part("given"),
+        von,
+        last: part("family"),
+        jr: part("suffix"),
+    };
+    if name.last.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Get the date of publication from `year` and `month`, or from `date`.
+fn issued(bibliography: &Bibliography) -> Option<Value> {
+    let year = match bibliography.get("year") {
+        Some(year) => year.trim(),
+        None => return bibliography.get("date").and_then(date_parts),
+    };
+    let year = match year.parse::<i64>() {
+        Ok(year) => year,
+        Err(_) => return Some(json!({ "literal": decode(year).text })),
+    };
+    let month = bibliography.get("month").and_then(|month| {
+        let month = month.trim().trim_end_matches('.').to_lowercase();
+        month.parse::<i64>().ok().or_else(|| {
+            MONTHS
+                .iter()
+                .position(|m| {
+                    let m = m.to_lowercase();
+                    m == month || (month.len() == 3 && m.starts_with(&month))
+                })
+                .map(|i| i as i64 + 1)
+        })
+    });
+    let parts = match month {
+        Some(month) => json!([year, month]),
+        None => json!([year]),
+    };
+    Some(json!({ "date-parts": [parts] }))
+}
+
+/// Convert an ISO date such as `2018-03-21` to CSL date parts.
+fn date_parts(date: &str) -> Option<Value> {
+    // Only the start of a range is kept.
+    let start = date.trim().split('/').next().unwrap_or("");
+    let parts = start
+        .split('-')
+        .map(|part| part.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some(json!({ "date-parts": [parts] }))
+}
+
+/// Get the year and the month name of a CSL date.
+fn issued_parts(date: &Value) -> Vec<String> {
+    if let Some(parts) = date["date-parts"][0].as_array() {
+        let mut result = parts.iter().take(2).filter_map(text).collect::<Vec<_>>();
+        if let Some(month) = result.get_mut(1) {
+            if let Some(name) = month
+                .parse::<usize>()
+                .ok()
+                .and_then(|m| MONTHS.get(m.wrapping_sub(1)))
+            {
+                *month = name.to_string();
+            }
+        }
+        return result;
+    }
+    date.get("literal")
+        .or_else(|| date.get("raw"))
+        .and_then(text)
+        .into_iter()
+        .collect()
+}
+
+/// Convert a CSL date to an ISO date such as `2018-03-21`.
+fn iso_date(date: &Value) -> Option<String> {
+    let parts = date["date-parts"][0].as_array()?;
+    let parts = parts
+        .iter()
+        .map(|part| text(part).map(|part| format!("{:0>2}", part)))
+        .collect::<Option<Vec<_>>>()?;
+    Some(parts.join("-"))
+}
+
+/// Get a string or a number as text.
+fn text(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref s) => Some(s.clone()),
+        Value::Number(ref n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(
+            date_parts("2018-03-21/2018-04"),
+            Some(json!({ "date-parts": [[2018, 3, 21]] }))
+        );
+        assert_eq!(date_parts("spring"), None);
+        assert_eq!(
+            issued_parts(&json!({ "date-parts": [[2018, 3, 21]] })),
+            vec!["2018", "March"]
+        );
+        assert_eq!(
+            issued_parts(&json!({ "date-parts": [["1905"]] })),
+            vec!["1905"]
+        );
+        assert_eq!(
+            issued_parts(&json!({ "raw": "ca. 1900" })),
+            vec!["ca. 1900"]
+        );
+        assert_eq!(
+            iso_date(&json!({ "date-parts": [[2018, 3, 1]] })),
+            Some("2018-03-01".into())
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            csl_name(&Name::parse("de la Vall{\\'e}e Poussin, Jr, Charles")),
+            json!({
+                "family": "Vallée Poussin",
+                "given": "Charles",
+                "non-dropping-particle": "de la",
+                "suffix": "Jr"
+            })
+        );
+        assert_eq!(
+            csl_name(&Name::parse("{Barnes and Noble}")),
+            json!({ "literal": "Barnes and Noble" })
+        );
+        assert_eq!(
+            bibtex_name(
+                &json!({ "family": "Beethoven", "given": "Ludwig", "non-dropping-particle": "van" })
+            ),
+            Some("van Beethoven, Ludwig".into())
+        );
+        assert_eq!(
+            bibtex_name(&json!({ "literal": "Barnes & Noble" })),
+            Some("{Barnes \\& Noble}".into())
+        );
+        assert_eq!(bibtex_name(&json!({ "given": "Nobody" })), None);
+    }
+}
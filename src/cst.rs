@@ -0,0 +1,461 @@
+//! A lossless concrete syntax tree of a *BibTeX* file content.
+//!
+//! Unlike `Bibtex`, a `Document` keeps everything written in the input: the
+//! whitespaces, the text between the entries, the case of the entry types,
+//! the delimiters and the `#` concatenations. Printing a document gives back
+//! the input byte for byte and editing it only changes the edited parts, so
+//! it can be used to modify a file without reformatting it.
+//!
+//! A document is built by the same parser as `Bibtex`, so it accepts the same
+//! inputs and reports the same errors. The comments which are not a `@comment{...}` group, such as the
+//! whole line after `@comment` with `CommentStyle::Bibtex`, are kept as text.
+//!
+//! ## Example
+//!
+//! ```
+//! use nom_bibtex::cst::{Document, Piece};
+//!
+//! let input = "% My references\n@Article{ key,\n  title = \"Old\" # suffix,\n  year  = 2018\n}\n";
+//! let mut document = Document::parse(input).unwrap();
+//! assert_eq!(document.to_string(), input);
+//!
+//! document
+//!     .entry_mut("key")
+//!     .unwrap()
+//!     .set_field("year", Piece::Number("2019".into()).into());
+//! assert_eq!(
+//!     document.to_string(),
+//!     "% My references\n@Article{ key,\n  title = \"Old\" # suffix,\n  year  = 2019\n}\n"
+//! );
+//! ```
+
+use error::{BibtexError, ParseError};
+use model::CommentStyle;
+pub use model::Delimiters;
+use parser::{self, Node};
+use std::borrow::Cow;
+use std::fmt;
+
+/// A whole *BibTeX* file content.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Document<'a> {
+    items: Vec<Item<'a>>,
+}
+
+/// A top level element of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item<'a> {
+    /// Any text outside of the entries, with the whitespaces.
+    Text(Cow<'a, str>),
+    /// An entry starting with a `@`.
+    Entry(Entry<'a>),
+}
+
+/// An entry such as `@article{key, title = {Title}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry<'a> {
+    /// The whitespaces between the `@` and the entry type.
+    pub before_type: Cow<'a, str>,
+    /// The entry type as written.
+    pub entry_type: Cow<'a, str>,
+    /// The whitespaces between the entry type and the opening delimiter.
+    pub after_type: Cow<'a, str>,
+    pub delimiters: Delimiters,
+    pub body: Body<'a>,
+}
+
+/// The content of an entry between its delimiters.
+///
+/// `before` and `end` hold the text written between the delimiters and the
+/// first and last elements of the body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body<'a> {
+    /// The raw content of a `@comment`.
+    Comment(Cow<'a, str>),
+    /// The value of a `@preamble`.
+    Preamble {
+        before: Cow<'a, str>,
+        value: Value<'a>,
+        end: Cow<'a, str>,
+    },
+    /// The single field of a `@string`.
+    String { field: Field<'a>, end: Cow<'a, str> },
+    /// The citation key and the fields of a bibliography entry.
+    Bibliography {
+        before: Cow<'a, str>,
+        citation_key: Cow<'a, str>,
+        fields: Vec<Field<'a>>,
+        end: Cow<'a, str>,
+    },
+}
+
+/// A `name = value` pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field<'a> {
+    /// The text before the name, including the separating comma if any.
+    pub before: Cow<'a, str>,
+    pub name: Cow<'a, str>,
+    /// The `=` with its surrounding whitespaces.
+    pub equals: Cow<'a, str>,
+    pub value: Value<'a>,
+}
+
+/// A value made of one or more pieces concatenated with `#`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value<'a> {
+    /// The pieces along with the text written before them. This text is
+    /// empty for the first piece and holds the `#` for the others.
+    pub pieces: Vec<(Cow<'a, str>, Piece<'a>)>,
+}
+
+/// A part of a value, holding its content without its delimiters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece<'a> {
+    /// `{content}`
+    Braced(Cow<'a, str>),
+    /// `"content"`
+    Quoted(Cow<'a, str>),
+    /// A number such as `2018`.
+    Number(Cow<'a, str>),
+    /// The name of a string variable.
+    Name(Cow<'a, str>),
+    /// Undelimited text, only found in preambles.
+    Raw(Cow<'a, str>),
+}
+
+impl<'a> Document<'a> {
+    /// Parse a *BibTeX* file content, with the comments of *biber*.
+    pub fn parse(input: &'a str) -> Result<Self, BibtexError> {
+        Self::parse_with(input, CommentStyle::Biber)
+    }
+
+    /// Parse a *BibTeX* file content with the given style of comments.
+    pub fn parse_with(input: &'a str, comments: CommentStyle) -> Result<Self, BibtexError> {
+        let nodes = match parser::nodes(input.as_bytes(), comments) {
+            Ok((_, nodes)) => nodes,
+            Err(e) => return Err(ParseError::from_nom(input, e).into()),
+        };
+
+        let mut document = Document::default();
+        for (source, node) in nodes {
+            match node {
+                Node::Entry(entry) => document.items.push(Item::Entry(entry)),
+                // The whitespaces and the comments without a type are kept
+                // as text.
+                Node::Whitespace | Node::Comment(_) => document.push_text(source),
+            }
+        }
+        Ok(document)
+    }
+
+    fn push_text(&mut self, text: &'a str) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(&mut Item::Text(ref mut previous)) = self.items.last_mut() {
+            previous.to_mut().push_str(text);
+            return;
+        }
+        self.items.push(Item::Text(text.into()));
+    }
+
+    /// Get the items in order.
+    pub fn items(&self) -> &Vec<Item<'a>> {
+        &self.items
+    }
+
+    /// Get the items in order to edit them.
+    pub fn items_mut(&mut self) -> &mut Vec<Item<'a>> {
+        &mut self.items
+    }
+
+    /// Get the entries in order.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.items.iter().filter_map(|item| match *item {
+            Item::Entry(ref entry) => Some(entry),
+            Item::Text(_) => None,
+        })
+    }
+
+    /// Get the entries in order to edit them.
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut Entry<'a>> {
+        self.items.iter_mut().filter_map(|item| match *item {
+            Item::Entry(ref mut entry) => Some(entry),
+            Item::Text(_) => None,
+        })
+    }
+
+    /// Get the first bibliography entry with the given citation key.
+    pub fn entry(&self, citation_key: &str) -> Option<&Entry<'a>> {
+        self.entries()
+            .find(|entry| entry.citation_key() == Some(citation_key))
+    }
+
+    /// Get the first bibliography entry with the given citation key to edit it.
+    pub fn entry_mut(&mut self, citation_key: &str) -> Option<&mut Entry<'a>> {
+        self.entries_mut()
+            .find(|entry| entry.citation_key() == Some(citation_key))
+    }
+}
+
+impl<'a> Entry<'a> {
+    /// Get the citation key of a bibliography entry.
+    pub fn citation_key(&self) -> Option<&str> {
+        match self.body {
+            Body::Bibliography {
+                ref citation_key, ..
+            } => Some(citation_key),
+            _ => None,
+        }
+    }
+
+    /// Get the fields of a bibliography entry or of a string variable.
+    pub fn fields(&self) -> &[Field<'a>] {
+        match self.body {
+            Body::Bibliography { ref fields, .. } => fields,
+            Body::String { ref field, .. } => ::std::slice::from_ref(field),
+            _ => &[],
+        }
+    }
+
+    /// Get the first field with the given name, ignoring the case.
+    pub fn field(&self, name: &str) -> Option<&Field<'a>> {
+        self.fields()
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get the first field with the given name, ignoring the case, to edit it.
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut Field<'a>> {
+        let fields = match self.body {
+            Body::Bibliography { ref mut fields, .. } => fields,
+            Body::String { ref mut field, .. } => ::std::slice::from_mut(field),
+            _ => return None,
+        };
+        fields
+            .iter_mut()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Set the value of a field of a bibliography entry.
+    ///
+    /// The value of an existing field is replaced in place. Otherwise the
+    /// field is added after the last one, with the same layout.
+    /// Nothing is done if the entry is not a bibliography entry.
+    pub fn set_field(&mut self, name: &str, value: Value<'a>) {
+        if let Some(field) = self.field_mut(name) {
+            field.value = value;
+            return;
+        }
+
+        if let Body::Bibliography {
+            ref mut fields,
+            ref mut end,
+            ..
+        } = self.body
+        {
+            let (before, equals) = match fields.last() {
+                Some(last) => (last.before.clone(), last.equals.clone()),
+                None => {
+                    // Move the comma following the citation key before the field.
+                    *end = end
+                        .trim_start_matches(&[',', ' ', '\t'][..])
+                        .to_string()
+                        .into();
+                    if !end.contains('\n') {
+                        *end = format!("\n{}", end).into();
+                    }
+                    (",\n    ".into(), " = ".into())
+                }
+            };
+            fields.push(Field {
+                before,
+                name: name.to_string().into(),
+                equals,
+                value,
+            });
+        }
+    }
+
+    /// Remove the first field with the given name, ignoring the case, from a
+    /// bibliography entry.
+    pub fn remove_field(&mut self, name: &str) -> Option<Field<'a>> {
+        if let Body::Bibliography {
+            ref mut fields,
+            ref mut end,
+            ..
+        } = self.body
+        {
+            let index = fields
+                .iter()
+                .position(|field| field.name.eq_ignore_ascii_case(name))?;
+            let field = fields.remove(index);
+            // The citation key must still be followed by a comma.
+            if fields.is_empty() && !end.contains(',') {
+                *end = format!(",{}", end).into();
+            }
+            return Some(field);
+        }
+        None
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Create a value concatenating the pieces with ` # `.
+    pub fn new(pieces: Vec<Piece<'a>>) -> Self {
+        let pieces = pieces
+            .into_iter()
+            .enumerate()
+            .map(|(i, piece)| (if i == 0 { "" } else { " # " }.into(), piece))
+            .collect();
+        Value { pieces }
+    }
+}
+
+impl<'a> From<Piece<'a>> for Value<'a> {
+    fn from(piece: Piece<'a>) -> Self {
+        Value::new(vec![piece])
+    }
+}
+
+impl<'a> fmt::Display for Document<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for item in &self.items {
+            match *item {
+                Item::Text(ref text) => f.write_str(text)?,
+                Item::Entry(ref entry) => write!(f, "{}", entry)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Entry<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "@{}{}{}{}",
+            self.before_type,
+            self.entry_type,
+            self.after_type,
+            self.delimiters.open()
+        )?;
+        match self.body {
+            Body::Comment(ref comment) => f.write_str(comment)?,
+            Body::Preamble {
+                ref before,
+                ref value,
+                ref end,
+            } => write!(f, "{}{}{}", before, value, end)?,
+            Body::String { ref field, ref end } => write!(f, "{}{}", field, end)?,
+            Body::Bibliography {
+                ref before,
+                ref citation_key,
+                ref fields,
+                ref end,
+            } => {
+                write!(f, "{}{}", before, citation_key)?;
+                for field in fields {
+                    write!(f, "{}", field)?;
+                }
+                f.write_str(end)?;
+            }
+        }
+        write!(f, "{}", self.delimiters.close())
+    }
+}
+
+impl<'a> fmt::Display for Field<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}",
+            self.before, self.name, self.equals, self.value
+        )
+    }
+}
+
+impl<'a> fmt::Display for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (before, piece) in &self.pieces {
+            write!(f, "{}{}", before, piece)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Piece<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Piece::Braced(ref content) => write!(f, "{{{}}}", content),
+            Piece::Quoted(ref content) => write!(f, "\"{}\"", content),
+            Piece::Number(ref content) | Piece::Name(ref content) | Piece::Raw(ref content) => {
+                f.write_str(content)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lossless() {
+        let input = "Some text\n\n@ Misc { key ,\n\ttitle=\"A  title\"#x ,\n\tyear = 2018 ,\n}\r\n\
+                     @STRING( name = \"Me\" )  @preamble{ raw {nested} text }\n@comment{ {nested} }";
+        let document = Document::parse(input).unwrap();
+        assert_eq!(document.to_string(), input);
+        assert_eq!(document.items().len(), 8);
+
+        let entry = document.entry("key").unwrap();
+        assert_eq!(entry.entry_type, "Misc");
+        assert_eq!(entry.delimiters, Delimiters::Braces);
+        assert_eq!(entry.fields().len(), 2);
+        assert_eq!(
+            entry.field("TITLE").unwrap().value.pieces,
+            vec![
+                ("".into(), Piece::Quoted("A  title".into())),
+                ("#".into(), Piece::Name("x".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_field() {
+        let mut document = Document::parse("@misc{key,\n  a = 1,\n  b = {2},\n}").unwrap();
+        {
+            let entry = document.entry_mut("key").unwrap();
+            entry.set_field("B", Piece::Quoted("two".into()).into());
+            entry.set_field(
+                "c",
+                Value::new(vec![Piece::Name("x".into()), Piece::Number("3".into())]),
+            );
+        }
+        assert_eq!(
+            document.to_string(),
+            "@misc{key,\n  a = 1,\n  b = \"two\",\n  c = x # 3,\n}"
+        );
+
+        let mut document = Document::parse("@misc{key,}").unwrap();
+        document
+            .entry_mut("key")
+            .unwrap()
+            .set_field("a", Piece::Number("1".into()).into());
+        assert_eq!(document.to_string(), "@misc{key,\n    a = 1\n}");
+    }
+
+    #[test]
+    fn test_remove_field() {
+        let mut document = Document::parse("@misc{key,\n  a = 1,\n  b = 2\n}").unwrap();
+        {
+            let entry = document.entry_mut("key").unwrap();
+            assert_eq!(entry.remove_field("b").unwrap().name, "b");
+            assert!(entry.remove_field("b").is_none());
+        }
+        assert_eq!(document.to_string(), "@misc{key,\n  a = 1\n}");
+
+        document.entry_mut("key").unwrap().remove_field("a");
+        assert_eq!(document.to_string(), "@misc{key,\n}");
+    }
+}
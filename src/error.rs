@@ -1,28 +1,367 @@
 use nom::Err;
-use std::error::Error;
+use parser;
+use span::{Position, SourceMap, Span};
+use std::fmt;
 
 quick_error! {
     #[derive(Debug, PartialEq, Eq)]
     pub enum BibtexError {
-        Parsing (descr: String) {
-            description(descr)
-            display(me) -> ("Parsing error. Reason: {}", me.description())
+        Parsing (err: ParseError) {
+            description("Parsing error.")
+            display("{}", err)
+            from()
         }
         StringVariableNotFound (var: String) {
             description("String variable not found.")
-            display(me) -> ("{}: {}", me.description(), var)
+            display("String variable not found.: {}", var)
         }
+        CyclicStringVariable (var: String) {
+            description("String variable defined from itself.")
+            display("String variable defined from itself.: {}", var)
+        }
+        CrossrefNotFound (key: String, crossref: String) {
+            description("Cross-referenced entry not found.")
+            display("Cross-referenced entry not found.: {} (from {})", crossref, key)
+        }
+        CyclicCrossref (key: String) {
+            description("Entry cross-referenced from itself.")
+            display("Entry cross-referenced from itself.: {}", key)
+        }
+        Duplicate (duplicate: Box<Duplicate>) {
+            description("Duplicate definition.")
+            display("Duplicate definition.: {}", duplicate)
+            from(duplicate: Duplicate) -> (Box::new(duplicate))
+        }
+    }
+}
+
+/// What is defined more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DuplicateKind {
+    /// The citation key of a bibliography.
+    CitationKey,
+    /// The name of a `@string` variable.
+    StringVariable,
+    /// The name of a tag in a bibliography.
+    Tag,
+}
+
+impl fmt::Display for DuplicateKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            DuplicateKind::CitationKey => "citation key",
+            DuplicateKind::StringVariable => "string variable",
+            DuplicateKind::Tag => "tag",
+        })
     }
 }
 
-// We cannot use the from() from quick_error, because we need to put lifetimes that we didn't
-// define.
-impl<T> From<Err<T>> for BibtexError {
-    fn from(err: Err<T>) -> BibtexError {
-        let descr = match err {
-            Err::Incomplete(e) => format!("Incomplete: {:?}", e),
-            Err::Error(e) | Err::Failure(e) => e.into_error_kind().description().into(),
+/// A citation key, a string variable or a tag defined more than once, with
+/// the locations of its first and of its later definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    kind: DuplicateKind,
+    name: String,
+    key: Option<String>,
+    first: Span,
+    second: Span,
+}
+
+impl Duplicate {
+    pub(crate) fn new(
+        kind: DuplicateKind,
+        name: &str,
+        key: Option<&str>,
+        first: Span,
+        second: Span,
+    ) -> Duplicate {
+        Duplicate {
+            kind,
+            name: name.into(),
+            key: key.map(String::from),
+            first,
+            second,
+        }
+    }
+
+    /// Get what is defined more than once.
+    pub fn kind(&self) -> DuplicateKind {
+        self.kind
+    }
+
+    /// Get the name as written in the later definition.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the citation key of the bibliography of a duplicate tag.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Get the location of the name in the first definition.
+    pub fn first(&self) -> Span {
+        self.first
+    }
+
+    /// Get the location of the name in the later definition.
+    pub fn second(&self) -> Span {
+        self.second
+    }
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} `{}`", self.kind, self.name)?;
+        if let Some(ref key) = self.key {
+            write!(f, " in entry `{}`", key)?;
+        }
+        write!(
+            f,
+            " at {}:{}, first defined at {}:{}",
+            self.second.start.line,
+            self.second.start.column,
+            self.first.start.line,
+            self.first.start.column
+        )
+    }
+}
+
+/// What the parser expected to find where it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Expected {
+    /// The start of a new entry or a comment.
+    Entry,
+    /// The type of an entry after the `@`.
+    EntryType,
+    /// A `{` or `(` opening the entry.
+    OpeningDelimiter,
+    /// A `}` closing the entry.
+    ClosingBrace,
+    /// A `)` closing the entry.
+    ClosingParenthesis,
+    /// A citation key followed by a `,` or the end of the entry.
+    CitationKey,
+    /// The name of a tag or a string variable.
+    Name,
+    /// A `=` between a name and its value.
+    Equals,
+    /// A value, either quoted, bracketed, a number or an abbreviation.
+    Value,
+    /// A comment enclosed in braces or parentheses.
+    Comment,
+    /// A `}` matching an opening brace of a value.
+    MatchingBrace,
+    /// A `"` matching an opening quote of a value.
+    MatchingQuote,
+    /// Anything else the parser could not understand.
+    Other,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let descr = match *self {
+            Expected::Entry => "an entry",
+            Expected::EntryType => "an entry type after `@`",
+            Expected::OpeningDelimiter => "`{` or `(`",
+            Expected::ClosingBrace => "`}`",
+            Expected::ClosingParenthesis => "`)`",
+            Expected::CitationKey => {
+                "a citation key without whitespaces or braces, followed by `,`"
+            }
+            Expected::Name => "a name",
+            Expected::Equals => "`=`",
+            Expected::Value => "a value",
+            Expected::Comment => "a comment enclosed in braces or parentheses",
+            Expected::MatchingBrace => "a `}` matching this `{`",
+            Expected::MatchingQuote => "a `\"` matching this `\"`",
+            Expected::Other => "valid BibTeX",
         };
-        BibtexError::Parsing(descr)
+        f.write_str(descr)
+    }
+}
+
+/// A parsing error with its location in the input.
+///
+/// Its `Display` implementation renders a compiler-like diagnostic:
+///
+/// ```text
+/// error: expected `=`
+///  --> 3:11
+///   |
+/// 3 |     title {Foo},
+///   |           ^
+///   = note: in entry `einstein`
+///   = note: while parsing a field, in a bibliography entry
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    position: Position,
+    key: Option<String>,
+    expected: Expected,
+    context: Vec<&'static str>,
+    snippet: String,
+}
+
+impl ParseError {
+    /// Build an error from the one returned by the parser on `input`.
+    pub(crate) fn from_nom(input: &str, err: Err<parser::Error>) -> ParseError {
+        match err {
+            Err::Incomplete(_) => Self::new(input, input.len(), Expected::Other),
+            Err::Error(e) | Err::Failure(e) => ParseError {
+                key: e.key.map(String::from),
+                context: e.context,
+                ..Self::new(input, input.len() - e.input.len(), e.expected)
+            },
+        }
+    }
+
+    /// Build an error located at the byte `offset` of `input`.
+    ///
+    /// The error is moved after any whitespace so that it points to the
+    /// unexpected content.
+    pub(crate) fn new(input: &str, offset: usize, expected: Expected) -> ParseError {
+        let mut offset = offset.min(input.len());
+        while !input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset += input[offset..].len() - input[offset..].trim_start().len();
+
+        let position = SourceMap::new(input).position(offset);
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |i| offset + i);
+        let snippet = input[line_start..line_end].trim_end_matches('\r').into();
+
+        ParseError {
+            position,
+            key: None,
+            expected,
+            context: vec![],
+            snippet,
+        }
+    }
+
+    /// Get the position of the error.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Get the line of the error, starting at 1.
+    pub fn line(&self) -> usize {
+        self.position.line
+    }
+
+    /// Get the column of the error, starting at 1.
+    pub fn column(&self) -> usize {
+        self.position.column
+    }
+
+    /// Get the byte offset of the error.
+    pub fn offset(&self) -> usize {
+        self.position.offset
+    }
+
+    /// Get the citation key (or string variable name) of the entry in
+    /// which the error occurred if it could be found.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Get what the parser expected to find.
+    pub fn expected(&self) -> Expected {
+        self.expected
+    }
+
+    /// Get the parts of the input which were being parsed, from the innermost
+    /// to the outermost, such as `["a field", "a bibliography entry"]`.
+    pub fn context(&self) -> &[&'static str] {
+        &self.context
+    }
+
+    /// Get the line of the input where the error occurred.
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = self.line().to_string();
+        let gutter = " ".repeat(line.len());
+        // Keep the tabs so that the marker is aligned with the snippet.
+        let marker_indent = self
+            .snippet
+            .chars()
+            .take(self.column() - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        writeln!(f, "error: expected {}", self.expected)?;
+        writeln!(f, "{}--> {}:{}", gutter, self.line(), self.column())?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line, self.snippet)?;
+        write!(f, "{} | {}^", gutter, marker_indent)?;
+        if let Some(ref key) = self.key {
+            write!(f, "\n{} = note: in entry `{}`", gutter, key)?;
+        }
+        if !self.context.is_empty() {
+            write!(
+                f,
+                "\n{} = note: while parsing {}",
+                gutter,
+                self.context.join(", in ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error() {
+        let input = "@misc{einstein,\n    author = \"A\",\n\ttitle {Foo},\n}";
+        let mut err = parser::Error::new(&input.as_bytes()[40..], Expected::Equals);
+        err.key = Some("einstein");
+        let err = ParseError::from_nom(input, Err::Failure(err));
+        assert_eq!(err.line(), 3);
+        assert_eq!(err.column(), 8);
+        assert_eq!(err.offset(), 41);
+        assert_eq!(err.key(), Some("einstein"));
+        assert_eq!(err.snippet(), "\ttitle {Foo},");
+        assert_eq!(
+            err.to_string(),
+            "error: expected `=`
+ --> 3:8
+  |
+3 | \ttitle {Foo},
+  | \t      ^
+  = note: in entry `einstein`"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_context() {
+        let input = "@misc{key, title = {A} # }";
+        let mut err = parser::Error::new(&input.as_bytes()[25..], Expected::Value);
+        err.context = vec!["a field", "a bibliography entry"];
+        let err = ParseError::from_nom(input, Err::Failure(err));
+        assert_eq!(err.context(), &["a field", "a bibliography entry"][..]);
+        assert_eq!(err.column(), 26);
+        assert!(err
+            .to_string()
+            .ends_with("= note: while parsing a field, in a bibliography entry"));
+    }
+
+    #[test]
+    fn test_parse_error_without_key() {
+        let err = ParseError::new("@preamble{ \"a\" ", 15, Expected::ClosingBrace);
+        assert_eq!(err.key(), None);
+        assert_eq!(err.column(), 16);
+        assert_eq!(err.expected(), Expected::ClosingBrace);
     }
 }
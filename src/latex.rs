@@ -0,0 +1,802 @@
+//! Convert between the *LaTeX* markup of the values and plain Unicode text.
+//!
+//! The values are returned verbatim by the parser, so a title such as
+//! `Zur Elektrodynamik bewegter K{\"o}rper` keeps its *LaTeX* commands.
+//! `decode` translates:
+//!
+//! - the accents such as `{\"o}`, `\'{e}`, `\v c` or `\'{\i}`,
+//! - the special characters such as `\ss`, `\o`, `\&` or `\LaTeX`,
+//! - the ligatures `--`, `---`, ` `` ` and `''`, and `~` to a no-break space,
+//! - the Greek letters and the usual symbols of the math mode, whose `$` are
+//!   removed,
+//! - the braces protecting the case, which are removed.
+//!
+//! The formatting commands such as `\emph` or `\textbf` are removed and
+//! their argument is kept. Any other command is reported as unknown, it's
+//! also removed but its argument is kept.
+//!
+//! The other way around, `encode` escapes a plain text to be written in a
+//! *BibTeX* file, see `Encoding`.
+//!
+//! ## Example
+//!
+//! ```
+//! use nom_bibtex::latex::{decode, encode, Encoding};
+//!
+//! let decoded = decode("Zur Elektrodynamik bewegter K{\\\"o}rper -- {\\'E}t{\\'e} $\\alpha$ \\foo{x}");
+//! assert_eq!(decoded.text, "Zur Elektrodynamik bewegter Körper – Été α x");
+//! assert_eq!(decoded.unknown_commands, vec!["\\foo"]);
+//!
+//! assert_eq!(encode("Körper – 50%", Encoding::Ascii), "K{\\\"o}rper -- 50\\%");
+//! ```
+
+use std::fmt::Write;
+
+/// The accent commands with their combining character and the letters they
+/// compose with, as pairs of a letter followed by the accented letter.
+const ACCENTS: &[(&str, char, &str)] = &[
+    ("`", '\u{0300}', "aàeèiìnǹoòuùwẁyỳAÀEÈIÌNǸOÒUÙWẀYỲ"),
+    (
+        "'",
+        '\u{0301}',
+        "aácćeégǵiíkḱlĺmḿnńoópṕrŕsśuúwẃyýzźAÁCĆEÉGǴIÍKḰLĹMḾNŃOÓPṔRŔSŚUÚWẂYÝZŹ",
+    ),
+    (
+        "^",
+        '\u{0302}',
+        "aâcĉeêgĝhĥiîjĵoôsŝuûwŵyŷzẑAÂCĈEÊGĜHĤIÎJĴOÔSŜUÛWŴYŶZẐ",
+    ),
+    ("~", '\u{0303}', "aãeẽiĩnñoõuũvṽyỹAÃEẼIĨNÑOÕUŨVṼYỸ"),
+    ("=", '\u{0304}', "aāeēgḡiīoōuūyȳAĀEĒGḠIĪOŌUŪYȲ"),
+    ("u", '\u{0306}', "aăeĕgğiĭoŏuŭAĂEĔGĞIĬOŎUŬ"),
+    (
+        ".",
+        '\u{0307}',
+        "aȧbḃcċdḋeėfḟgġhḣmṁnṅoȯpṗrṙsṡtṫwẇxẋyẏzżAȦBḂCĊDḊEĖFḞGĠHḢIİMṀNṄOȮPṖRṘSṠTṪWẆXẊYẎZŻ",
+    ),
+    ("\"", '\u{0308}', "aäeëhḧiïoötẗuüwẅxẍyÿAÄEËHḦIÏOÖUÜWẄXẌYŸ"),
+    ("r", '\u{030a}', "aåuůwẘyẙAÅUŮ"),
+    ("H", '\u{030b}', "oőuűOŐUŰ"),
+    (
+        "v",
+        '\u{030c}',
+        "aǎcčdďeěgǧhȟiǐjǰkǩlľnňoǒrřsštťuǔzžAǍCČDĎEĚGǦHȞIǏKǨLĽNŇOǑRŘSŠTŤUǓZŽ",
+    ),
+    (
+        "d",
+        '\u{0323}',
+        "aạbḅdḍeẹhḥiịkḳlḷmṃnṇoọrṛsṣtṭuụvṿwẉyỵzẓAẠBḄDḌEẸHḤIỊKḲLḶMṂNṆOỌRṚSṢTṬUỤVṾWẈYỴZẒ",
+    ),
+    (
+        "c",
+        '\u{0327}',
+        "cçdḑeȩgģhḩkķlļnņrŗsştţCÇDḐEȨGĢHḨKĶLĻNŅRŖSŞTŢ",
+    ),
+    ("k", '\u{0328}', "aąeęiįoǫuųAĄEĘIĮOǪUŲ"),
+    ("b", '\u{0331}', "bḇdḏhẖkḵlḻnṉrṟtṯzẕBḆDḎKḴLḺNṈRṞTṮZẔ"),
+];
+
+/// The accents written alone, as in `\~{}`.
+const SPACING_ACCENTS: &[(&str, &str)] = &[
+    ("`", "`"),
+    ("'", "´"),
+    ("^", "^"),
+    ("~", "~"),
+    ("=", "¯"),
+    ("u", "˘"),
+    (".", "˙"),
+    ("\"", "¨"),
+    ("r", "˚"),
+    ("H", "˝"),
+    ("v", "ˇ"),
+    ("c", "¸"),
+    ("k", "˛"),
+];
+
+/// The commands producing a text.
+const SYMBOLS: &[(&str, &str)] = &[
+    // Special characters.
+    ("ss", "ß"),
+    ("SS", "SS"),
+    ("o", "ø"),
+    ("O", "Ø"),
+    ("ae", "æ"),
+    ("AE", "Æ"),
+    ("oe", "œ"),
+    ("OE", "Œ"),
+    ("aa", "å"),
+    ("AA", "Å"),
+    ("l", "ł"),
+    ("L", "Ł"),
+    ("i", "ı"),
+    ("j", "ȷ"),
+    ("dh", "ð"),
+    ("DH", "Ð"),
+    ("th", "þ"),
+    ("TH", "Þ"),
+    ("dj", "đ"),
+    ("DJ", "Đ"),
+    ("ng", "ŋ"),
+    ("NG", "Ŋ"),
+    // Control symbols.
+    ("&", "&"),
+    ("%", "%"),
+    ("$", "$"),
+    ("#", "#"),
+    ("_", "_"),
+    ("{", "{"),
+    ("}", "}"),
+    (" ", " "),
+    ("\\", " "),
+    (",", "\u{2009}"),
+    (";", " "),
+    (":", " "),
+    ("-", ""),
+    ("/", ""),
+    ("@", ""),
+    ("!", ""),
+    // Text symbols.
+    ("S", "§"),
+    ("P", "¶"),
+    ("copyright", "©"),
+    ("textcopyright", "©"),
+    ("textregistered", "®"),
+    ("texttrademark", "™"),
+    ("pounds", "£"),
+    ("textsterling", "£"),
+    ("euro", "€"),
+    ("texteuro", "€"),
+    ("dag", "†"),
+    ("ddag", "‡"),
+    ("textdagger", "†"),
+    ("textdaggerdbl", "‡"),
+    ("dots", "…"),
+    ("ldots", "…"),
+    ("textellipsis", "…"),
+    ("textendash", "–"),
+    ("textemdash", "—"),
+    ("textquoteleft", "‘"),
+    ("textquoteright", "’"),
+    ("textquotedblleft", "“"),
+    ("textquotedblright", "”"),
+    ("guillemotleft", "«"),
+    ("guillemotright", "»"),
+    ("guillemetleft", "«"),
+    ("guillemetright", "»"),
+    ("textbackslash", "\\"),
+    ("textasciitilde", "~"),
+    ("textasciicircum", "^"),
+    ("textbraceleft", "{"),
+    ("textbraceright", "}"),
+    ("textunderscore", "_"),
+    ("textbar", "|"),
+    ("textless", "<"),
+    ("textgreater", ">"),
+    ("textdegree", "°"),
+    ("textbullet", "•"),
+    ("textperiodcentered", "·"),
+    ("quad", "\u{2003}"),
+    ("qquad", "\u{2003}\u{2003}"),
+    ("TeX", "TeX"),
+    ("LaTeX", "LaTeX"),
+    ("LaTeXe", "LaTeX2ε"),
+    ("BibTeX", "BibTeX"),
+];
+
+/// The commands of the math mode producing a text.
+const MATH_SYMBOLS: &[(&str, &str)] = &[
+    // Greek letters.
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("varpi", "ϖ"),
+    ("rho", "ρ"),
+    ("varrho", "ϱ"),
+    ("sigma", "σ"),
+    ("varsigma", "ς"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Upsilon", "Υ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    // Math symbols.
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("times", "×"),
+    ("div", "÷"),
+    ("cdot", "·"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("sim", "∼"),
+    ("equiv", "≡"),
+    ("infty", "∞"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("leftrightarrow", "↔"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("sqrt", "√"),
+    ("in", "∈"),
+    ("subset", "⊂"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("emptyset", "∅"),
+    ("ell", "ℓ"),
+    ("hbar", "ℏ"),
+    ("circ", "∘"),
+    ("prime", "′"),
+];
+
+/// The formatting commands, which are removed while their argument is kept.
+const FORMATTING: &[&str] = &[
+    "emph",
+    "textit",
+    "textbf",
+    "textsc",
+    "texttt",
+    "textrm",
+    "textsf",
+    "textsl",
+    "textup",
+    "textmd",
+    "textnormal",
+    "textsuperscript",
+    "textsubscript",
+    "mathrm",
+    "mathbf",
+    "mathit",
+    "mathsf",
+    "mathtt",
+    "mathcal",
+    "mathbb",
+    "ensuremath",
+    "text",
+    "mbox",
+    "hbox",
+    "it",
+    "bf",
+    "em",
+    "sc",
+    "rm",
+    "tt",
+    "sl",
+    "sf",
+    "normalfont",
+    "itshape",
+    "bfseries",
+    "scshape",
+    "upshape",
+    "mdseries",
+    "relax",
+    "protect",
+    "nocase",
+];
+
+const SUPERSCRIPTS: &str = "0⁰1¹2²3³4⁴5⁵6⁶7⁷8⁸9⁹+⁺-⁻=⁼(⁽)⁾nⁿi";
+const SUBSCRIPTS: &str = "0₀1₁2₂3₃4₄5₅6₆7₇8₈9₉+₊-₋=₌(₍)₎";
+
+/// The characters of the plain text escaped by `encode`.
+const ESCAPED: &[(char, &str)] = &[
+    ('\\', "{\\textbackslash}"),
+    // *BibTeX* counts the braces even when they are escaped.
+    ('{', "{\\textbraceleft}"),
+    ('}', "{\\textbraceright}"),
+    ('&', "\\&"),
+    ('%', "\\%"),
+    ('$', "\\$"),
+    ('#', "\\#"),
+    ('_', "\\_"),
+    ('~', "{\\textasciitilde}"),
+    ('^', "{\\textasciicircum}"),
+];
+
+/// The characters written with ligatures by `encode`.
+const LIGATURES: &[(char, &str)] = &[
+    ('–', "--"),
+    ('—', "---"),
+    ('“', "``"),
+    ('”', "''"),
+    ('‘', "`"),
+    ('’', "'"),
+    ('¡', "!`"),
+    ('¿', "?`"),
+    ('\u{a0}', "~"),
+];
+
+/// The result of `decode`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Decoded {
+    /// The plain Unicode text.
+    pub text: String,
+    /// The commands which could not be translated, such as `\foo`, without
+    /// duplicates and in the order they appear.
+    pub unknown_commands: Vec<String>,
+}
+
+/// Translate a value written with *LaTeX* markup into plain Unicode text.
+///
+/// The whitespaces are collapsed into a single space and trimmed.
+pub fn decode(value: &str) -> Decoded {
+    let mut decoder = Decoder {
+        chars: value.chars().collect(),
+        pos: 0,
+        math: false,
+        unknown_commands: vec![],
+    };
+    let text = decoder.decode_all();
+
+    let mut collapsed = String::with_capacity(text.len());
+    for word in text.split(|c: char| c.is_whitespace() && c != '\u{a0}' && c != '\u{2009}') {
+        if !word.is_empty() {
+            if !collapsed.is_empty() {
+                collapsed.push(' ');
+            }
+            collapsed.push_str(word);
+        }
+    }
+    Decoded {
+        text: collapsed,
+        unknown_commands: decoder.unknown_commands,
+    }
+}
+
+/// How `encode` escapes a plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Only escape the characters which have a meaning in *LaTeX*, such as
+    /// `&`, `%` or `{`, and keep the other characters as is. It suits the
+    /// tools reading UTF-8 such as *biber*.
+    Minimal,
+    /// Also write every non-ASCII character which has a *LaTeX* equivalent
+    /// with commands or ligatures, such as `{\"o}`, `{\ss}`, `--` or
+    /// `$\alpha$`, for the classic 8-bit *BibTeX*. The characters without
+    /// an equivalent are kept as is.
+    Ascii,
+}
+
+/// Escape a plain text to be written as a value of a *BibTeX* file.
+///
+/// The text is not expected to contain *LaTeX* markup, so a `\` or a `{` is
+/// escaped too.
+pub fn encode(text: &str, encoding: Encoding) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(&(_, escaped)) = ESCAPED.iter().find(|e| e.0 == c) {
+            out.push_str(escaped);
+        } else if encoding == Encoding::Minimal {
+            out.push(c);
+        } else if let Some(accented) = chars.peek().and_then(|&next| decompose(c, next)) {
+            // A letter followed by a combining character.
+            chars.next();
+            out.push_str(&accented);
+        } else if c.is_ascii() {
+            out.push(c);
+        } else if let Some(&(_, ligature)) = LIGATURES.iter().find(|l| l.0 == c) {
+            out.push_str(ligature);
+        } else if let Some(accented) = decompose_precomposed(c) {
+            out.push_str(&accented);
+        } else if let Some(&(name, _)) = SYMBOLS.iter().find(|s| s.1 == c.to_string()) {
+            if name.chars().all(|c| c.is_ascii_alphabetic()) {
+                write!(out, "{{\\{}}}", name).expect("Writing to a String can't fail");
+            } else {
+                write!(out, "\\{}", name).expect("Writing to a String can't fail");
+            }
+        } else if let Some(&(name, _)) = MATH_SYMBOLS.iter().find(|s| s.1 == c.to_string()) {
+            write!(out, "$\\{}$", name).expect("Writing to a String can't fail");
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Write a letter with an accent given as a combining character.
+fn decompose(base: char, combining: char) -> Option<String> {
+    let &(command, _, _) = ACCENTS.iter().find(|a| a.1 == combining)?;
+    Some(accented(command, base))
+}
+
+/// Write a precomposed accented letter.
+fn decompose_precomposed(c: char) -> Option<String> {
+    ACCENTS.iter().find_map(|&(command, _, letters)| {
+        let letters = letters.chars().collect::<Vec<_>>();
+        letters
+            .chunks(2)
+            .find(|pair| pair[1] == c)
+            .map(|pair| accented(command, pair[0]))
+    })
+}
+
+fn accented(command: &str, base: char) -> String {
+    if command.chars().all(|c| c.is_ascii_alphabetic()) {
+        format!("{{\\{} {}}}", command, base)
+    } else {
+        format!("{{\\{}{}}}", command, base)
+    }
+}
+
+/// Get the letter composed of `base` with the accent `command`.
+fn compose(command: &str, base: char) -> Option<String> {
+    // The dotless letters are used to put an accent on `i` and `j`.
+    let base = match base {
+        'ı' => 'i',
+        'ȷ' => 'j',
+        c => c,
+    };
+    let &(_, combining, letters) = ACCENTS.iter().find(|a| a.0 == command)?;
+    let letters = letters.chars().collect::<Vec<_>>();
+    let composed = letters.chunks(2).find(|pair| pair[0] == base).map_or_else(
+        || format!("{}{}", base, combining),
+        |pair| pair[1].to_string(),
+    );
+    Some(composed)
+}
+
+struct Decoder {
+    chars: Vec<char>,
+    pos: usize,
+    math: bool,
+    unknown_commands: Vec<String>,
+}
+
+impl Decoder {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next_is(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn decode_all(&mut self) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\\' => {
+                    let decoded = self.command();
+                    out.push_str(&decoded);
+                }
+                '{' | '}' => {}
+                '$' => self.math = !self.math,
+                '~' => out.push('\u{a0}'),
+                '-' if !self.math && self.next_is("--") => {
+                    self.pos += 2;
+                    out.push('—');
+                }
+                '-' if !self.math && self.next_is("-") => {
+                    self.pos += 1;
+                    out.push('–');
+                }
+                '`' if self.next_is("`") => {
+                    self.pos += 1;
+                    out.push('“');
+                }
+                '\'' if self.next_is("'") => {
+                    self.pos += 1;
+                    out.push('”');
+                }
+                '!' if self.next_is("`") => {
+                    self.pos += 1;
+                    out.push('¡');
+                }
+                '?' if self.next_is("`") => {
+                    self.pos += 1;
+                    out.push('¿');
+                }
+                '^' if self.math => {
+                    let argument = self.argument();
+                    out.push_str(&script(&argument, SUPERSCRIPTS));
+                }
+                '_' if self.math => {
+                    let argument = self.argument();
+                    out.push_str(&script(&argument, SUBSCRIPTS));
+                }
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// Decode a command whose `\` is already consumed.
+    fn command(&mut self) -> String {
+        let name = match self.peek() {
+            None => return String::new(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                let name = self.chars[start..self.pos].iter().collect::<String>();
+                // The spaces are kept in the math mode to separate the symbols.
+                if !self.math {
+                    self.skip_whitespace();
+                }
+                name
+            }
+            Some(c) => {
+                self.pos += 1;
+                c.to_string()
+            }
+        };
+
+        if ACCENTS.iter().any(|a| a.0 == name) {
+            return self.accent(&name);
+        }
+        if let Some(&(_, text)) = SYMBOLS.iter().chain(MATH_SYMBOLS).find(|s| s.0 == name) {
+            return text.into();
+        }
+        if FORMATTING.contains(&name.as_ref()) {
+            return String::new();
+        }
+        match name.as_ref() {
+            // Only used to sort the entries.
+            "noopsort" => {
+                self.raw_argument();
+                String::new()
+            }
+            "url" => self.raw_argument(),
+            "href" => {
+                self.raw_argument();
+                String::new()
+            }
+            _ => {
+                let command = format!("\\{}", name);
+                if !self.unknown_commands.contains(&command) {
+                    self.unknown_commands.push(command);
+                }
+                String::new()
+            }
+        }
+    }
+
+    fn accent(&mut self, command: &str) -> String {
+        let argument = self.argument();
+        let mut chars = argument.chars();
+        match chars.next() {
+            None => SPACING_ACCENTS
+                .iter()
+                .find(|a| a.0 == command)
+                .map_or("", |a| a.1)
+                .into(),
+            Some(base) => {
+                let mut composed = compose(command, base).unwrap_or_else(|| base.to_string());
+                composed.extend(chars);
+                composed
+            }
+        }
+    }
+
+    /// Decode the argument of a command, either a group or a single
+    /// character or command.
+    fn argument(&mut self) -> String {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                let raw = self.raw_argument();
+                let mut decoder = Decoder {
+                    chars: raw.chars().collect(),
+                    pos: 0,
+                    math: self.math,
+                    unknown_commands: vec![],
+                };
+                let decoded = decoder.decode_all();
+                for command in decoder.unknown_commands {
+                    if !self.unknown_commands.contains(&command) {
+                        self.unknown_commands.push(command);
+                    }
+                }
+                decoded
+            }
+            Some('\\') => {
+                self.pos += 1;
+                self.command()
+            }
+            Some(c) => {
+                self.pos += 1;
+                c.to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Get the content of the next group without decoding it, or the next
+    /// character if there is no group.
+    fn raw_argument(&mut self) -> String {
+        self.skip_whitespace();
+        if self.peek() != Some('{') {
+            return self.peek().map_or_else(String::new, |c| {
+                self.pos += 1;
+                c.to_string()
+            });
+        }
+        self.pos += 1;
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        let end = if self.chars.get(self.pos - 1) == Some(&'}') {
+            self.pos - 1
+        } else {
+            self.pos
+        };
+        self.chars[start..end].iter().collect()
+    }
+}
+
+/// Write a superscript or a subscript with the given characters, as pairs of
+/// a character followed by its script version, if possible.
+fn script(argument: &str, scripts: &str) -> String {
+    let scripts = scripts.chars().collect::<Vec<_>>();
+    let converted = argument
+        .chars()
+        .map(|c| {
+            scripts
+                .chunks(2)
+                .find(|pair| pair[0] == c)
+                .map(|pair| pair[1])
+        })
+        .collect::<Option<String>>();
+    converted.unwrap_or_else(|| argument.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> String {
+        decode(value).text
+    }
+
+    #[test]
+    fn test_accents() {
+        assert_eq!(text("K{\\\"o}rper"), "Körper");
+        assert_eq!(text("K\\\"orper"), "Körper");
+        assert_eq!(text("{\\'e}t{\\'E}"), "étÉ");
+        assert_eq!(text("\\'{e}\\`{a}\\^o"), "éàô");
+        assert_eq!(text("\\v c\\v{S}"), "čŠ");
+        assert_eq!(text("\\'{\\i}\\^\\j"), "íĵ");
+        assert_eq!(
+            text("\\c{c}\\k{a}\\H{o}\\r{u}\\u{g}\\={a}\\.{z}\\d{s}"),
+            "çąőůğāżṣ"
+        );
+        assert_eq!(text("\\~{}uno"), "~uno");
+        // Without a precomposed letter, a combining character is used.
+        assert_eq!(text("\\\"{q}"), "q\u{0308}");
+    }
+
+    #[test]
+    fn test_symbols() {
+        assert_eq!(text("Stra\\ss e"), "Straße");
+        assert_eq!(text("{\\o}{\\AE}\\oe{}"), "øÆœ");
+        assert_eq!(text("Smith \\& Sons, 50\\%"), "Smith & Sons, 50%");
+        assert_eq!(text("The \\LaTeX\\ Companion"), "The LaTeX Companion");
+        assert_eq!(text("891--921 --- ``quoted''"), "891–921 — “quoted”");
+        assert_eq!(text("Dr.~Who"), "Dr.\u{a0}Who");
+        assert_eq!(text("!`Hola!"), "¡Hola!");
+    }
+
+    #[test]
+    fn test_math() {
+        assert_eq!(text("$\\alpha$-helix and $\\Omega$"), "α-helix and Ω");
+        assert_eq!(text("$x^2 + H_{2}O$, $10^{-3}$"), "x² + H₂O, 10⁻³");
+        assert_eq!(text("$a \\leq b$"), "a ≤ b");
+    }
+
+    #[test]
+    fn test_braces_and_commands() {
+        assert_eq!(
+            text("{Zur Elektrodynamik} ({German})\n        [{On} the bodies]"),
+            "Zur Elektrodynamik (German) [On the bodies]"
+        );
+        assert_eq!(text("\\emph{Very} \\textbf{bold}"), "Very bold");
+        assert_eq!(text("{\\noopsort{a}}Title"), "Title");
+        assert_eq!(text("\\url{http://a.org/~me--x}"), "http://a.org/~me--x");
+
+        let decoded = decode("\\foo{bar} \\baz \\foo");
+        assert_eq!(decoded.text, "bar");
+        assert_eq!(decoded.unknown_commands, vec!["\\foo", "\\baz"]);
+
+        assert_eq!(decode("\\'{\\foo}").unknown_commands, vec!["\\foo"]);
+        assert_eq!(text("unclosed {\\'e"), "unclosed é");
+        assert_eq!(text("\\"), "");
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            encode("Körper & Straße: 50% of $5_000 {x}", Encoding::Minimal),
+            "Körper \\& Straße: 50\\% of \\$5\\_000 {\\textbraceleft}x{\\textbraceright}"
+        );
+        assert_eq!(
+            encode("Körper & Straße", Encoding::Ascii),
+            "K{\\\"o}rper \\& Stra{\\ss}e"
+        );
+        assert_eq!(
+            encode("Été — pages 1–2, “quoted”, čŞ", Encoding::Ascii),
+            "{\\'E}t{\\'e} --- pages 1--2, ``quoted'', {\\v c}{\\c S}"
+        );
+        assert_eq!(
+            encode("a\u{0308} ≤ α", Encoding::Ascii),
+            "{\\\"a} $\\leq$ $\\alpha$"
+        );
+        assert_eq!(
+            encode("a\\b~c^d", Encoding::Ascii),
+            "a{\\textbackslash}b{\\textasciitilde}c{\\textasciicircum}d"
+        );
+        // Without an equivalent, a character is kept.
+        assert_eq!(encode("漢字", Encoding::Ascii), "漢字");
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for text in &[
+            "Körper & Straße",
+            "Été — pages 1–2, “quoted”",
+            "Gödel, Escher, Bach: 50% of $5_000 {x}",
+            "a { b } } c",
+            "Łódź ø æ œ å ı ¡¿ a\\b",
+            "α ≤ β × γ",
+        ] {
+            let encoded = encode(text, Encoding::Ascii);
+            assert!(encoded.is_ascii(), "{}", encoded);
+            assert_eq!(&decode(&encoded).text, text);
+            assert_eq!(&decode(&encode(text, Encoding::Minimal)).text, text);
+        }
+    }
+}
@@ -59,14 +59,40 @@
 //! }
 //! ```
 //!
-#[macro_use]
+//! ## Features
+//!
+//! - `serde`: implement `Serialize` and `Deserialize` for the model, and
+//!   deserialize a bibliography into a struct. See the `serialization` module.
+//! - `csl`: convert the bibliographies to and from CSL-JSON. See the `csl`
+//!   module.
+//!
 extern crate nom;
 #[macro_use]
 extern crate quick_error;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "csl")]
+#[macro_use]
+extern crate serde_json;
 
+pub mod crossref;
+#[cfg(feature = "csl")]
+pub mod csl;
+pub mod cst;
 pub mod error;
+pub mod latex;
 pub mod model;
+pub mod names;
 mod parser;
+pub mod ris;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod span;
+pub mod validate;
+pub mod writer;
 
-pub use model::{Bibliography, Bibtex};
+pub use model::{Bibliography, Bibtex, ParseOptions};
 pub use parser::Entry;
+pub use span::{Position, Span};
+pub use writer::Writer;
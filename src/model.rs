@@ -1,60 +1,373 @@
-use error::BibtexError;
-use nom::types::CompleteByteSlice;
+use crossref::{self, InheritanceRules};
+use error::{BibtexError, Duplicate, DuplicateKind, ParseError};
+use names::{parse_names, Name};
 use parser;
 use parser::Entry;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use span::{SourceMap, Span};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::result;
 use std::str;
 
 type Result<T> = result::Result<T, BibtexError>;
 
+/// The month abbreviations predefined by the standard *BibTeX* styles.
+pub const MONTH_MACROS: [(&str, &str); 12] = [
+    ("jan", "January"),
+    ("feb", "February"),
+    ("mar", "March"),
+    ("apr", "April"),
+    ("may", "May"),
+    ("jun", "June"),
+    ("jul", "July"),
+    ("aug", "August"),
+    ("sep", "September"),
+    ("oct", "October"),
+    ("nov", "November"),
+    ("dec", "December"),
+];
+
+/// What to do with the citation keys, string variables and tags defined
+/// more than once.
+///
+/// The duplicates are always reported by `Bibtex::duplicates`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Report each duplicate as an error and keep the first definition.
+    Error,
+    /// Keep the first definition, as *BibTeX* does for the entries and the
+    /// tags.
+    FirstWins,
+    /// Keep the last definition, as *BibTeX* does for the string variables.
+    LastWins,
+}
+
+/// How the body of a `@comment` entry is delimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentStyle {
+    /// The comment is the rest of the line after `@comment`, as in classic
+    /// *BibTeX* which ignores the text between the entries anyway. An entry
+    /// written on the next lines is parsed even if it is enclosed in the
+    /// braces of the comment.
+    Bibtex,
+    /// The comment is a group enclosed in balanced braces or parentheses,
+    /// as in *biber*.
+    Biber,
+}
+
+/// Options to configure how a *BibTeX* file content is turned into a `Bibtex`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    expand_variables: bool,
+    macros: HashMap<String, String>,
+    case_sensitive_keys: bool,
+    duplicates: DuplicatePolicy,
+    comments: CommentStyle,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            expand_variables: true,
+            macros: MONTH_MACROS
+                .iter()
+                .map(|&(name, value)| (name.into(), value.into()))
+                .collect(),
+            case_sensitive_keys: false,
+            duplicates: DuplicatePolicy::FirstWins,
+            comments: CommentStyle::Biber,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the string variables by their values, which is the default.
+    ///
+    /// When disabled, an abbreviation is kept as its name in the values so
+    /// an undefined variable is not an error. The structure of the values
+    /// is still available with `Bibliography::raw_values`.
+    pub fn expand_variables(mut self, expand_variables: bool) -> Self {
+        self.expand_variables = expand_variables;
+        self
+    }
+
+    /// Compare the citation keys with their case, as *LaTeX* does.
+    ///
+    /// By default, the case is ignored like *BibTeX* does, so `Bibtex::get`
+    /// finds `Knuth` with `knuth`.
+    pub fn case_sensitive_keys(mut self, case_sensitive_keys: bool) -> Self {
+        self.case_sensitive_keys = case_sensitive_keys;
+        self
+    }
+
+    /// Set what to do with the definitions repeated in a file, which is
+    /// `DuplicatePolicy::FirstWins` by default.
+    ///
+    /// The citation keys are compared following `case_sensitive_keys`, the
+    /// string variables and the tags of a bibliography without their case.
+    pub fn duplicates(mut self, duplicates: DuplicatePolicy) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Set how the `@comment` entries are delimited, which is
+    /// `CommentStyle::Biber` by default.
+    pub fn comments(mut self, comments: CommentStyle) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Add predefined macros, which are used to expand the abbreviations
+    /// not defined by a `@string` of the file.
+    ///
+    /// Like the abbreviations, the names are case-insensitive and they are
+    /// stored in lowercase. The month macros of `MONTH_MACROS` are predefined
+    /// by default.
+    pub fn macros<I, K, V>(mut self, macros: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.macros.extend(
+            macros
+                .into_iter()
+                .map(|(name, value)| (normalize_name(&name.into()), value.into())),
+        );
+        self
+    }
+
+    /// Add the string variables of another *BibTeX* file content, such as a
+    /// list of journal abbreviations, as predefined macros.
+    ///
+    /// The file is parsed with the current options so it can use the macros
+    /// which are already defined.
+    pub fn macros_from_bibtex(self, bibtex: &str) -> Result<Self> {
+        let variables = Bibtex::parse_with(bibtex, &self)?.variables;
+        Ok(self.macros(variables))
+    }
+
+    /// Remove all the predefined macros, including the month macros.
+    pub fn clear_macros(mut self) -> Self {
+        self.macros.clear();
+        self
+    }
+
+    /// Get the predefined macros.
+    pub fn predefined_macros(&self) -> &HashMap<String, String> {
+        &self.macros
+    }
+}
+
 /// A high-level definition of a bibtex file.
-#[derive(Debug, PartialEq, Eq, Default)]
+///
+/// Equality only compares the content, not where it is located in the input.
+#[derive(Debug, Eq, Default)]
 pub struct Bibtex<'a> {
-    comments: Vec<&'a str>,
+    comments: Vec<Cow<'a, str>>,
     preambles: Vec<String>,
     variables: Vec<(String, String)>,
     bibliographies: Vec<Bibliography<'a>>,
+    comment_spans: Vec<Span>,
+    preamble_spans: Vec<Span>,
+    variable_spans: Vec<(Span, Span)>,
+    /// The index of the first bibliography of each citation key, normalized
+    /// with `normalize_name` unless `case_sensitive_keys` is set.
+    key_index: HashMap<String, usize>,
+    case_sensitive_keys: bool,
+    duplicates: Vec<Duplicate>,
 }
 
 impl<'a> Bibtex<'a> {
     /// Create a new Bibtex instance from a *BibTeX* file content.
     pub fn parse(bibtex: &'a str) -> Result<Self> {
-        let entries = Self::raw_parse(bibtex)?;
+        Self::parse_with(bibtex, &ParseOptions::default())
+    }
+
+    /// Create a new Bibtex instance from a *BibTeX* file content with the
+    /// given options.
+    pub fn parse_with(bibtex: &'a str, options: &ParseOptions) -> Result<Self> {
+        let entries = Self::parse_with_source(bibtex, options.comments)?;
+        let (bibtex, errors) = Self::from_entries(bibtex, entries, options);
+
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(bibtex),
+        }
+    }
+
+    /// Create a new Bibtex instance from a *BibTeX* file content, skipping
+    /// the entries which can't be parsed or expanded instead of failing.
+    ///
+    /// A malformed entry is skipped up to the next `@`. All the errors are
+    /// returned in order along with the entries which were successfully parsed.
+    pub fn parse_lenient(bibtex: &'a str) -> (Self, Vec<BibtexError>) {
+        Self::parse_lenient_with(bibtex, &ParseOptions::default())
+    }
+
+    /// Same as `parse_lenient` with the given options.
+    pub fn parse_lenient_with(bibtex: &'a str, options: &ParseOptions) -> (Self, Vec<BibtexError>) {
+        let (entries, parsing_errors) =
+            parser::entries_lenient(bibtex.as_bytes(), options.comments);
+        let mut errors = parsing_errors
+            .into_iter()
+            .map(|e| ParseError::from_nom(bibtex, e).into())
+            .collect::<Vec<BibtexError>>();
+
+        let (bibtex, expansion_errors) = Self::from_entries(bibtex, entries, options);
+        errors.extend(expansion_errors);
+        (bibtex, errors)
+    }
+
+    /// Build an instance from the parsed entries, skipping the ones which
+    /// can't be expanded.
+    fn from_entries(
+        input: &'a str,
+        entries: Vec<(&'a str, Entry<'a>)>,
+        options: &ParseOptions,
+    ) -> (Self, Vec<BibtexError>) {
+        let source_map = SourceMap::new(input);
 
         let mut bibtex = Bibtex::default();
+        let mut errors = vec![];
 
-        Self::fill_variables(&mut bibtex, &entries)?;
+        Self::fill_variables(&mut bibtex, &entries, &source_map, options, &mut errors);
 
-        for entry in entries {
+        for (source, entry) in entries {
             match entry {
                 Entry::Variable(_) => continue, // Already handled.
-                Entry::Comment(v) => bibtex.comments.push(v),
-                Entry::Preamble(v) => {
-                    let new_val = Self::expand_str_abbreviations(v, &bibtex)?;
-                    bibtex.preambles.push(new_val);
+                Entry::Comment(v) => {
+                    bibtex.comments.push(v.into());
+                    bibtex.comment_spans.push(source_map.span_of(source));
                 }
-                Entry::Bibliography(entry_t, citation_key, tags) => {
-                    let mut new_tags = vec![];
-                    for tag in tags {
-                        new_tags.push((
-                            tag.key.into(),
-                            Self::expand_str_abbreviations(tag.value, &bibtex)?,
-                        ))
+                Entry::Preamble(v) => match Self::expand_str_abbreviations(&v, &bibtex, options) {
+                    Ok(new_val) => {
+                        bibtex.preambles.push(new_val);
+                        bibtex.preamble_spans.push(source_map.span_of(source));
+                    }
+                    Err(e) => errors.push(e),
+                },
+                Entry::Bibliography(entry_t, citation_key, tags, delimiters) => {
+                    let (duplicates, kept) = find_duplicates(
+                        tags.iter().map(|tag| normalize_name(tag.key)),
+                        options.duplicates,
+                    );
+                    for (first, second) in duplicates {
+                        let duplicate = Duplicate::new(
+                            DuplicateKind::Tag,
+                            tags[second].key,
+                            Some(citation_key),
+                            source_map.span_of(tags[first].key),
+                            source_map.span_of(tags[second].key),
+                        );
+                        bibtex.report(duplicate, options, &mut errors);
+                    }
+                    let tags = tags
+                        .into_iter()
+                        .zip(kept)
+                        .filter_map(|(tag, kept)| if kept { Some(tag) } else { None })
+                        .collect::<Vec<_>>();
+
+                    let tag_spans = tags
+                        .iter()
+                        .map(|tag| {
+                            (
+                                source_map.span_of(tag.key),
+                                source_map.span_of(tag.value_source),
+                            )
+                        })
+                        .collect();
+                    let new_tags = tags
+                        .iter()
+                        .map(|tag| {
+                            let value =
+                                Self::expand_str_abbreviations(&tag.value, &bibtex, options)?;
+                            Ok((tag.key.into(), value))
+                        })
+                        .collect::<Result<Vec<_>>>();
+
+                    match new_tags {
+                        Ok(new_tags) => {
+                            let mut bibliography =
+                                Bibliography::new(entry_t, citation_key, new_tags);
+                            bibliography.delimiters = delimiters;
+                            bibliography.span = source_map.span_of(source);
+                            bibliography.citation_key_span = source_map.span_of(citation_key);
+                            bibliography.tag_spans = tag_spans;
+                            bibliography.raw_values =
+                                tags.into_iter().map(|tag| tag.value).collect();
+                            bibtex.bibliographies.push(bibliography);
+                        }
+                        Err(e) => errors.push(e),
                     }
-                    bibtex
-                        .bibliographies
-                        .push(Bibliography::new(entry_t, citation_key, new_tags));
                 }
             }
         }
-        Ok(bibtex)
+
+        bibtex.case_sensitive_keys = options.case_sensitive_keys;
+        let (duplicates, kept) = find_duplicates(
+            bibtex
+                .bibliographies
+                .iter()
+                .map(|bibliography| bibtex.index_key(&bibliography.citation_key))
+                .collect::<Vec<_>>(),
+            options.duplicates,
+        );
+        for (first, second) in duplicates {
+            let duplicate = Duplicate::new(
+                DuplicateKind::CitationKey,
+                &bibtex.bibliographies[second].citation_key,
+                None,
+                bibtex.bibliographies[first].citation_key_span,
+                bibtex.bibliographies[second].citation_key_span,
+            );
+            bibtex.report(duplicate, options, &mut errors);
+        }
+        let mut kept = kept.into_iter();
+        bibtex
+            .bibliographies
+            .retain(|_| kept.next().unwrap_or(true));
+
+        bibtex.index_keys();
+        bibtex
+            .duplicates
+            .sort_by_key(|duplicate| duplicate.second().start.offset);
+        (bibtex, errors)
+    }
+
+    /// Record a duplicate definition, as an error too if the policy says so.
+    fn report(
+        &mut self,
+        duplicate: Duplicate,
+        options: &ParseOptions,
+        errors: &mut Vec<BibtexError>,
+    ) {
+        if options.duplicates == DuplicatePolicy::Error {
+            errors.push(duplicate.clone().into());
+        }
+        self.duplicates.push(duplicate);
     }
 
     /// Get a raw vector of entries in order from the files.
     pub fn raw_parse(bibtex: &'a str) -> Result<Vec<Entry<'a>>> {
-        match parser::entries(CompleteByteSlice(bibtex.as_bytes())) {
+        let entries = Self::parse_with_source(bibtex, CommentStyle::Biber)?;
+        Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    fn parse_with_source(
+        bibtex: &'a str,
+        comments: CommentStyle,
+    ) -> Result<Vec<(&'a str, Entry<'a>)>> {
+        match parser::entries(bibtex.as_bytes(), comments) {
             Ok((_, v)) => Ok(v),
-            Err(e) => Err(From::from(e)),
+            Err(e) => Err(ParseError::from_nom(bibtex, e).into()),
         }
     }
 
@@ -63,99 +376,363 @@ impl<'a> Bibtex<'a> {
         &self.preambles
     }
 
-    /// Get comments.
-    pub fn comments(&self) -> &Vec<&str> {
+    /// Get the location of the preambles, in the same order as `preambles`.
+    pub fn preamble_spans(&self) -> &Vec<Span> {
+        &self.preamble_spans
+    }
+
+    /// Get comments, borrowed from the input unless they were deserialized
+    /// from escaped strings.
+    pub fn comments(&self) -> &Vec<Cow<'a, str>> {
         &self.comments
     }
 
+    /// Get the location of the comments, in the same order as `comments`.
+    pub fn comment_spans(&self) -> &Vec<Span> {
+        &self.comment_spans
+    }
+
     /// Get string variables with a tuple of key and expanded value.
     pub fn variables(&self) -> &Vec<(String, String)> {
         &self.variables
     }
 
+    /// Get the location of the string variables as a tuple of key and value
+    /// spans, in the same order as `variables`.
+    pub fn variable_spans(&self) -> &Vec<(Span, Span)> {
+        &self.variable_spans
+    }
+
     /// Get bibliographies entry with variables expanded.
-    pub fn bibliographies(&self) -> &Vec<Bibliography> {
+    pub fn bibliographies(&self) -> &Vec<Bibliography<'_>> {
         &self.bibliographies
     }
 
-    fn fill_variables(bibtex: &mut Bibtex, entries: &[Entry]) -> Result<()> {
+    /// Get the bibliography with the given citation key, ignoring its case
+    /// unless `ParseOptions::case_sensitive_keys` is set.
+    ///
+    /// If the key is repeated, the first bibliography is returned.
+    pub fn get(&self, key: &str) -> Option<&Bibliography<'a>> {
+        self.key_index
+            .get(&self.index_key(key))
+            .map(|&i| &self.bibliographies[i])
+    }
+
+    /// Build an instance from its content, as if it was parsed with the
+    /// default options.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_content(
+        comments: Vec<Cow<'a, str>>,
+        preambles: Vec<String>,
+        variables: Vec<(String, String)>,
+        bibliographies: Vec<Bibliography<'a>>,
+    ) -> Self {
+        let mut bibtex = Bibtex {
+            comment_spans: vec![Span::default(); comments.len()],
+            preamble_spans: vec![Span::default(); preambles.len()],
+            variable_spans: vec![Default::default(); variables.len()],
+            comments,
+            preambles,
+            variables,
+            bibliographies,
+            ..Bibtex::default()
+        };
+        bibtex.index_keys();
+        bibtex
+    }
+
+    fn index_keys(&mut self) {
+        self.key_index.clear();
+        for (i, bibliography) in self.bibliographies.iter().enumerate() {
+            let key = self.index_key(&bibliography.citation_key);
+            self.key_index.entry(key).or_insert(i);
+        }
+    }
+
+    fn index_key(&self, key: &str) -> String {
+        if self.case_sensitive_keys {
+            key.into()
+        } else {
+            normalize_name(key)
+        }
+    }
+
+    /// Get the citation keys, string variables and tags defined more than
+    /// once, in the order of their later definition.
+    ///
+    /// Only the definition kept following `ParseOptions::duplicates` is
+    /// part of the content.
+    pub fn duplicates(&self) -> &Vec<Duplicate> {
+        &self.duplicates
+    }
+
+    /// Get the bibliographies with the fields inherited from the entries
+    /// they cross-reference, following the given rules.
+    ///
+    /// The targets are looked up ignoring the case of the citation keys,
+    /// unless `ParseOptions::case_sensitive_keys` is set. `bibliographies`
+    /// is left untouched. See the `crossref` module for the details of the
+    /// inheritance.
+    pub fn resolve_crossrefs(
+        &self,
+        rules: InheritanceRules,
+    ) -> (Vec<Bibliography<'a>>, Vec<BibtexError>) {
+        crossref::resolve(&self.bibliographies, rules, self.case_sensitive_keys)
+    }
+
+    fn fill_variables(
+        bibtex: &mut Bibtex,
+        entries: &[(&str, Entry)],
+        source_map: &SourceMap,
+        options: &ParseOptions,
+        errors: &mut Vec<BibtexError>,
+    ) {
         let variables = entries
             .iter()
             .filter_map(|v| match v {
-                &Entry::Variable(ref v) => Some(v),
+                (_, Entry::Variable(v)) => Some(v),
                 _ => None,
-            }).collect::<Vec<_>>();
+            })
+            .collect::<Vec<_>>();
 
-        for var in &variables {
-            bibtex.variables.push((
-                var.key.into(),
-                Self::expand_variables_value(&var.value, &variables)?,
-            ));
+        let (duplicates, kept) = find_duplicates(
+            variables.iter().map(|v| normalize_name(v.key)),
+            options.duplicates,
+        );
+        for (first, second) in duplicates {
+            let duplicate = Duplicate::new(
+                DuplicateKind::StringVariable,
+                variables[second].key,
+                None,
+                source_map.span_of(variables[first].key),
+                source_map.span_of(variables[second].key),
+            );
+            bibtex.report(duplicate, options, errors);
         }
+        let variables = variables
+            .into_iter()
+            .zip(kept)
+            .filter_map(|(v, kept)| if kept { Some(v) } else { None })
+            .collect::<Vec<_>>();
 
-        Ok(())
+        for (i, var) in variables.iter().enumerate() {
+            let value = if options.expand_variables {
+                Self::expand_variables_value(&var.value, &variables, &options.macros, &mut vec![i])
+            } else {
+                Ok(Self::unexpanded_value(&var.value))
+            };
+            match value {
+                Ok(value) => {
+                    bibtex.variables.push((var.key.into(), value));
+                    bibtex.variable_spans.push((
+                        source_map.span_of(var.key),
+                        source_map.span_of(var.value_source),
+                    ));
+                }
+                Err(e) => errors.push(e),
+            }
+        }
     }
 
+    /// Expand a string variable value, `expanding` holds the indexes of the
+    /// variables currently being expanded to detect cycles.
     fn expand_variables_value(
-        var_values: &Vec<StringValueType>,
-        variables: &Vec<&KeyValue>,
+        var_values: &[StringValueType],
+        variables: &[&KeyValue],
+        macros: &HashMap<String, String>,
+        expanding: &mut Vec<usize>,
     ) -> Result<String> {
         let mut result_value = String::new();
 
         for chunck in var_values {
             match *chunck {
-                StringValueType::Str(v) => result_value.push_str(v),
+                StringValueType::Str(v) | StringValueType::Number(v) => result_value.push_str(v),
                 StringValueType::Abbreviation(v) => {
-                    let var = variables
+                    let index = match variables
                         .iter()
-                        .find(|&&x| v == x.key)
-                        .ok_or_else(|| BibtexError::StringVariableNotFound(v.into()))?;
-                    result_value.push_str(&Self::expand_variables_value(&var.value, &variables)?);
+                        .position(|&x| v.eq_ignore_ascii_case(x.key))
+                    {
+                        Some(index) => index,
+                        None => {
+                            let value = macros
+                                .get(&normalize_name(v))
+                                .ok_or_else(|| BibtexError::StringVariableNotFound(v.into()))?;
+                            result_value.push_str(value);
+                            continue;
+                        }
+                    };
+                    if expanding.contains(&index) {
+                        return Err(BibtexError::CyclicStringVariable(v.into()));
+                    }
+                    expanding.push(index);
+                    let value = Self::expand_variables_value(
+                        &variables[index].value,
+                        variables,
+                        macros,
+                        expanding,
+                    )?;
+                    expanding.pop();
+                    result_value.push_str(&value);
                 }
             }
         }
         Ok(result_value)
     }
 
-    fn expand_str_abbreviations(value: Vec<StringValueType>, bibtex: &Bibtex) -> Result<String> {
+    fn expand_str_abbreviations(
+        value: &[StringValueType],
+        bibtex: &Bibtex,
+        options: &ParseOptions,
+    ) -> Result<String> {
+        if !options.expand_variables {
+            return Ok(Self::unexpanded_value(value));
+        }
         let mut result = String::new();
 
         for chunck in value {
-            match chunck {
-                StringValueType::Str(v) => result.push_str(v),
+            match *chunck {
+                StringValueType::Str(v) | StringValueType::Number(v) => result.push_str(v),
                 StringValueType::Abbreviation(v) => {
-                    let var = bibtex
+                    let value = bibtex
                         .variables
                         .iter()
-                        .find(|&x| v == x.0)
+                        .find(|&x| v.eq_ignore_ascii_case(&x.0))
+                        .map(|x| &x.1)
+                        .or_else(|| options.macros.get(&normalize_name(v)))
                         .ok_or_else(|| BibtexError::StringVariableNotFound(v.into()))?;
-                    result.push_str(&var.1);
+                    result.push_str(value);
                 }
             }
         }
         Ok(result)
     }
+
+    /// Join the parts of a value, keeping the name of the abbreviations.
+    fn unexpanded_value(value: &[StringValueType]) -> String {
+        value.iter().map(StringValueType::as_str).collect()
+    }
+}
+
+impl<'a> PartialEq for Bibtex<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.comments == other.comments
+            && self.preambles == other.preambles
+            && self.variables == other.variables
+            && self.bibliographies == other.bibliographies
+    }
+}
+
+/// Normalize a citation key, a tag name or a string variable name so that
+/// they are compared ignoring the case. Like in *BibTeX*, only the ASCII
+/// letters are case-folded, as with `eq_ignore_ascii_case`.
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.to_ascii_lowercase()
+}
+
+/// Find the names defined more than once, as pairs of the indexes of the
+/// first and of a later definition, and whether each definition is kept
+/// following the policy.
+fn find_duplicates<I>(names: I, policy: DuplicatePolicy) -> (Vec<(usize, usize)>, Vec<bool>)
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut definitions = HashMap::new();
+    let mut count = 0;
+    for (i, name) in names.into_iter().enumerate() {
+        definitions.entry(name).or_insert_with(Vec::new).push(i);
+        count += 1;
+    }
+
+    let mut duplicates = vec![];
+    let mut kept = vec![true; count];
+    for indexes in definitions.values().filter(|indexes| indexes.len() > 1) {
+        duplicates.extend(indexes[1..].iter().map(|&i| (indexes[0], i)));
+        let winner = match policy {
+            DuplicatePolicy::LastWins => indexes[indexes.len() - 1],
+            DuplicatePolicy::Error | DuplicatePolicy::FirstWins => indexes[0],
+        };
+        for &i in indexes.iter().filter(|&&i| i != winner) {
+            kept[i] = false;
+        }
+    }
+    duplicates.sort_by_key(|&(_, second)| second);
+    (duplicates, kept)
+}
+
+/// The delimiters enclosing the body of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiters {
+    /// `{ ... }`
+    Braces,
+    /// `( ... )`
+    Parentheses,
+}
+
+impl Delimiters {
+    /// Get the opening delimiter.
+    pub fn open(self) -> char {
+        match self {
+            Delimiters::Braces => '{',
+            Delimiters::Parentheses => '(',
+        }
+    }
+
+    /// Get the closing delimiter.
+    pub fn close(self) -> char {
+        match self {
+            Delimiters::Braces => '}',
+            Delimiters::Parentheses => ')',
+        }
+    }
 }
 
 /// This is the main representation of a bibliography.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Equality only compares the entry type, the citation key and the tags, not
+/// the raw values, the delimiters nor where it is located in the input.
+#[derive(Debug, Clone, Eq)]
 pub struct Bibliography<'a> {
-    entry_type: &'a str,
-    citation_key: &'a str,
+    entry_type: Cow<'a, str>,
+    citation_key: Cow<'a, str>,
     tags: Vec<(String, String)>,
+    raw_values: Vec<Vec<StringValueType<'a>>>,
+    delimiters: Delimiters,
+    span: Span,
+    citation_key_span: Span,
+    tag_spans: Vec<(Span, Span)>,
+    /// The index of the first tag of each lowercase name.
+    index: HashMap<String, usize>,
 }
 
 impl<'a> Bibliography<'a> {
     /// Create a new bibliography.
-    pub fn new(
-        entry_type: &'a str,
-        citation_key: &'a str,
+    ///
+    /// The entry type and the citation key are either borrowed or owned, for
+    /// example when they are converted from another format.
+    pub fn new<T, K>(
+        entry_type: T,
+        citation_key: K,
         tags: Vec<(String, String)>,
-    ) -> Bibliography<'a> {
+    ) -> Bibliography<'a>
+    where
+        T: Into<Cow<'a, str>>,
+        K: Into<Cow<'a, str>>,
+    {
+        let tag_spans = vec![Default::default(); tags.len()];
+        let mut index = HashMap::new();
+        for (i, tag) in tags.iter().enumerate() {
+            index.entry(normalize_name(&tag.0)).or_insert(i);
+        }
         Bibliography {
-            entry_type,
-            citation_key,
+            entry_type: entry_type.into(),
+            citation_key: citation_key.into(),
+            raw_values: vec![vec![]; tags.len()],
             tags,
+            delimiters: Delimiters::Braces,
+            span: Span::default(),
+            citation_key_span: Span::default(),
+            tag_spans,
+            index,
         }
     }
 
@@ -163,7 +740,7 @@ impl<'a> Bibliography<'a> {
     ///
     /// It represents the type of the publications such as article, book, ...
     pub fn entry_type(&self) -> &str {
-        self.entry_type
+        &self.entry_type
     }
 
     /// Get the citation key.
@@ -171,7 +748,7 @@ impl<'a> Bibliography<'a> {
     /// The citation key is the the keyword used to reference the bibliography
     /// in a LaTeX file for example.
     pub fn citation_key(&self) -> &str {
-        self.citation_key
+        &self.citation_key
     }
 
     /// Get the tags.
@@ -181,31 +758,170 @@ impl<'a> Bibliography<'a> {
     pub fn tags(&self) -> &Vec<(String, String)> {
         &self.tags
     }
+
+    /// Get the value of a tag, ignoring the case of its name.
+    ///
+    /// If the tag is repeated, the first value is returned.
+    pub fn get(&self, tag: &str) -> Option<&str> {
+        self.index
+            .get(&normalize_name(tag))
+            .map(|&i| self.tags[i].1.as_ref())
+    }
+
+    /// Get the value of the `title` tag.
+    pub fn title(&self) -> Option<&str> {
+        self.get("title")
+    }
+
+    /// Get the year from the `year` tag, or from the `date` tag used by
+    /// *biblatex* such as `2018-03-21`.
+    ///
+    /// A year which is not a number, such as `to appear` or `2018a`, is
+    /// ignored and the `date` tag is used instead.
+    pub fn year(&self) -> Option<i32> {
+        if let Some(year) = self.get("year").and_then(|year| year.trim().parse().ok()) {
+            return Some(year);
+        }
+        let date = self.get("date")?.trim();
+        let end = date
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(date.len());
+        date[..end].parse().ok()
+    }
+
+    /// Get the names listed in the `author` tag.
+    pub fn authors(&self) -> Vec<Name> {
+        self.names("author")
+    }
+
+    /// Get the names listed in the `editor` tag.
+    pub fn editors(&self) -> Vec<Name> {
+        self.names("editor")
+    }
+
+    /// Get the names listed in a tag, ignoring the case of its name.
+    pub fn names(&self, tag: &str) -> Vec<Name> {
+        self.get(tag).map_or_else(Vec::new, parse_names)
+    }
+
+    /// Get the values of the tags as written, before the string variables
+    /// are expanded, in the same order as `tags`.
+    ///
+    /// The values are empty for a bibliography created with `new`.
+    pub fn raw_values(&self) -> &Vec<Vec<StringValueType<'a>>> {
+        &self.raw_values
+    }
+
+    /// Get the delimiters enclosing the entry, kept to write it back the
+    /// same way.
+    ///
+    /// They are braces for a bibliography created with `new`.
+    pub fn delimiters(&self) -> Delimiters {
+        self.delimiters
+    }
+
+    /// Set the delimiters enclosing the entry.
+    pub fn set_delimiters(&mut self, delimiters: Delimiters) {
+        self.delimiters = delimiters;
+    }
+
+    /// Get the location of the whole entry in the input.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Get the location of the citation key in the input.
+    pub fn citation_key_span(&self) -> Span {
+        self.citation_key_span
+    }
+
+    /// Get the location of the tags as a tuple of key and value spans,
+    /// in the same order as `tags`.
+    pub fn tag_spans(&self) -> &Vec<(Span, Span)> {
+        &self.tag_spans
+    }
+
+    /// Copy the tag at `index` of `other` under the name `key`, along with
+    /// its raw value and its location.
+    pub(crate) fn inherit_tag(&mut self, other: &Bibliography<'a>, index: usize, key: &str) {
+        self.index
+            .entry(normalize_name(key))
+            .or_insert(self.tags.len());
+        self.tags.push((key.into(), other.tags[index].1.clone()));
+        self.raw_values.push(other.raw_values[index].clone());
+        self.tag_spans.push(other.tag_spans[index]);
+    }
+}
+
+impl<'a> PartialEq for Bibliography<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entry_type == other.entry_type
+            && self.citation_key == other.citation_key
+            && self.tags == other.tags
+    }
 }
 
 /// Represent a Bibtex value which is composed of
 ///
 /// - strings value
+/// - numbers
 /// - string variable/abbreviation which will be expanded after parsing.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum StringValueType<'a> {
     /// Just a basic string.
     Str(&'a str),
+    /// An undelimited number.
+    Number(&'a str),
     /// An abbreviation that should match some string variable.
     Abbreviation(&'a str),
 }
 
+impl<'a> StringValueType<'a> {
+    /// Get the string, the number or the name of the abbreviation.
+    pub fn as_str(&self) -> &'a str {
+        match *self {
+            StringValueType::Str(v)
+            | StringValueType::Number(v)
+            | StringValueType::Abbreviation(v) => v,
+        }
+    }
+}
+
 /// Representation of a key-value.
 ///
 /// Only used by parsing.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KeyValue<'a> {
     pub key: &'a str,
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub value: Vec<StringValueType<'a>>,
+    /// The value as written in the input, with its delimiters.
+    pub value_source: &'a str,
 }
 
 impl<'a> KeyValue<'a> {
     pub fn new(key: &'a str, value: Vec<StringValueType<'a>>) -> KeyValue<'a> {
-        Self { key, value: value }
+        Self::with_source(key, value, "")
+    }
+
+    pub fn with_source(
+        key: &'a str,
+        value: Vec<StringValueType<'a>>,
+        value_source: &'a str,
+    ) -> KeyValue<'a> {
+        Self {
+            key,
+            value,
+            value_source,
+        }
+    }
+}
+
+/// The value source is only a location in the input so it's not compared.
+impl<'a> PartialEq for KeyValue<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.value == other.value
     }
 }
@@ -0,0 +1,382 @@
+//! Parse the person names of fields such as `author` and `editor`.
+//!
+//! The names are split on the ` and ` which are not enclosed in braces, then
+//! each name is split in four parts following the *BibTeX* rules:
+//!
+//! - `First von Last`
+//! - `von Last, First`
+//! - `von Last, Jr, First`
+//!
+//! The `von` part is made of the words starting with a lowercase letter, a
+//! word enclosed in braces like `{Barnes and Noble}` has no case and is never
+//! split. The `Last` part always has at least one word.
+//!
+//! ## Example
+//!
+//! ```
+//! use nom_bibtex::names::{parse_names, Name};
+//!
+//! let names = parse_names("Ludwig van Beethoven and Ford, Jr., Henry and others");
+//! assert_eq!(names[0], Name::new("Ludwig", "van", "Beethoven", ""));
+//! assert_eq!(names[1], Name::new("Henry", "", "Ford", "Jr."));
+//! assert!(names[2].is_others());
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A person name split in its four parts. A missing part is empty.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Name {
+    pub first: String,
+    pub von: String,
+    pub last: String,
+    pub jr: String,
+}
+
+impl Name {
+    /// Create a name from its parts.
+    pub fn new(first: &str, von: &str, last: &str, jr: &str) -> Self {
+        Name {
+            first: first.into(),
+            von: von.into(),
+            last: last.into(),
+            jr: jr.into(),
+        }
+    }
+
+    /// Parse a single name.
+    pub fn parse(name: &str) -> Self {
+        let parts = split_commas(name)
+            .into_iter()
+            .map(words)
+            .collect::<Vec<_>>();
+
+        match parts.len() {
+            0 => Name::default(),
+            1 => {
+                let words = &parts[0];
+                if words.is_empty() {
+                    return Name::default();
+                }
+                let last = words.len() - 1;
+                match words[..last].iter().position(|w| is_lowercase(w.1)) {
+                    None => Name {
+                        first: join(&words[..last]),
+                        last: join(&words[last..]),
+                        ..Name::default()
+                    },
+                    Some(von_start) => {
+                        let von_end = words[..last]
+                            .iter()
+                            .rposition(|w| is_lowercase(w.1))
+                            .map_or(von_start, |i| i + 1);
+                        Name {
+                            first: join(&words[..von_start]),
+                            von: join(&words[von_start..von_end]),
+                            last: join(&words[von_end..]),
+                            jr: String::new(),
+                        }
+                    }
+                }
+            }
+            _ => {
+                let (von, last) = split_von_last(&parts[0]);
+                // Extra commas are kept in the first part.
+                let (jr, first) = if parts.len() == 2 {
+                    (String::new(), join(&parts[1]))
+                } else {
+                    let first = parts[2..]
+                        .iter()
+                        .map(|words| join(words))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    (join(&parts[1]), first)
+                };
+                Name {
+                    first,
+                    von,
+                    last,
+                    jr,
+                }
+            }
+        }
+    }
+
+    /// Check if the name is the `others` keyword, which stands for the
+    /// names which are not listed.
+    pub fn is_others(&self) -> bool {
+        self.first.is_empty() && self.von.is_empty() && self.jr.is_empty() && self.last == "others"
+    }
+}
+
+/// Write the name as `von Last, Jr, First`, which is parsed back to the
+/// same name.
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.von.is_empty() {
+            write!(f, "{} ", self.von)?;
+        }
+        f.write_str(&self.last)?;
+        if !self.jr.is_empty() {
+            write!(f, ", {}", self.jr)?;
+        }
+        if !self.first.is_empty() || !self.jr.is_empty() {
+            write!(f, ", {}", self.first)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a list of names separated by ` and `.
+pub fn parse_names(names: &str) -> Vec<Name> {
+    let mut result = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let bytes = names.as_bytes();
+    for (i, &c) in bytes.iter().enumerate() {
+        match c {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            // An `and` surrounded by whitespaces.
+            b'a' | b'A'
+                if depth == 0
+                    && i > start
+                    && bytes[i - 1].is_ascii_whitespace()
+                    && bytes.len() > i + 3
+                    && bytes[i..i + 3].eq_ignore_ascii_case(b"and")
+                    && bytes[i + 3].is_ascii_whitespace() =>
+            {
+                result.push(&names[start..i]);
+                start = i + 3;
+            }
+            _ => {}
+        }
+    }
+    result.push(&names[start..]);
+
+    result
+        .into_iter()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(Name::parse)
+        .collect()
+}
+
+/// Split a name on the commas which are not enclosed in braces.
+fn split_commas(name: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in name.bytes().enumerate() {
+        match c {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                parts.push(&name[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&name[start..]);
+    parts
+}
+
+/// Split a part of a name in words, along with the separator preceding each
+/// word, which is a space, a `-` or a `~`.
+fn words(part: &str) -> Vec<(char, &str)> {
+    let mut words = vec![];
+    let mut depth = 0;
+    let mut start = None;
+    let mut separator = ' ';
+    for (i, c) in part.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        let is_separator = depth == 0 && (c.is_whitespace() || c == '-' || c == '~');
+        match (is_separator, start) {
+            (true, Some(s)) => {
+                words.push((separator, &part[s..i]));
+                start = None;
+                separator = if c.is_whitespace() { ' ' } else { c };
+            }
+            (true, None) => {
+                if !c.is_whitespace() {
+                    separator = c;
+                }
+            }
+            (false, None) => start = Some(i),
+            (false, Some(_)) => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((separator, &part[s..]));
+    }
+    words
+}
+
+/// Join the words with their separators.
+fn join(words: &[(char, &str)]) -> String {
+    let mut result = String::new();
+    for (i, &(separator, word)) in words.iter().enumerate() {
+        if i > 0 {
+            result.push(separator);
+        }
+        result.push_str(word);
+    }
+    result
+}
+
+/// Split the words before the first comma in the `von` and `Last` parts.
+fn split_von_last(words: &[(char, &str)]) -> (String, String) {
+    if words.is_empty() {
+        return (String::new(), String::new());
+    }
+    let last = words.len() - 1;
+    match words[..last].iter().rposition(|w| is_lowercase(w.1)) {
+        Some(von_end) => (join(&words[..=von_end]), join(&words[von_end + 1..])),
+        None => (String::new(), join(words)),
+    }
+}
+
+/// Check if a word starts with a lowercase letter, ignoring what is enclosed
+/// in braces except the special characters such as `{\'e}`.
+fn is_lowercase(word: &str) -> bool {
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '{' {
+            if chars.peek() != Some(&'\\') {
+                // A brace group has no case.
+                return false;
+            }
+            chars.next();
+            let command = chars
+                .clone()
+                .take_while(|c| c.is_alphabetic())
+                .collect::<String>();
+            if ["oe", "OE", "ae", "AE", "aa", "AA", "o", "O", "l", "L", "ss"]
+                .contains(&command.as_ref())
+            {
+                return command.starts_with(char::is_lowercase);
+            }
+            // The case of an accented letter such as `{\'e}` or `{\v c}`.
+            return chars
+                .skip(command.chars().count())
+                .take_while(|&c| c != '}')
+                .find(|c| c.is_alphabetic())
+                .is_some_and(char::is_lowercase);
+        }
+        if c.is_alphabetic() {
+            return c.is_lowercase();
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_von_last() {
+        assert_eq!(
+            Name::parse("Donald Knuth"),
+            Name::new("Donald", "", "Knuth", "")
+        );
+        assert_eq!(
+            Name::parse("Charles Louis Xavier Joseph de la Vall{\\'e}e Poussin"),
+            Name::new(
+                "Charles Louis Xavier Joseph",
+                "de la",
+                "Vall{\\'e}e Poussin",
+                ""
+            )
+        );
+        assert_eq!(
+            Name::parse("jean de la fontaine"),
+            Name::new("", "jean de la", "fontaine", "")
+        );
+        assert_eq!(
+            Name::parse("Jean-Paul   Sartre"),
+            Name::new("Jean-Paul", "", "Sartre", "")
+        );
+        assert_eq!(
+            Name::parse("{\\'E}mile Zola"),
+            Name::new("{\\'E}mile", "", "Zola", "")
+        );
+        assert_eq!(
+            Name::parse("Jean {\\'e}douard {Poussin}"),
+            Name::new("Jean", "{\\'e}douard", "{Poussin}", "")
+        );
+        assert_eq!(Name::parse("Aristotle"), Name::new("", "", "Aristotle", ""));
+    }
+
+    #[test]
+    fn test_von_last_first() {
+        assert_eq!(
+            Name::parse("van Beethoven, Ludwig"),
+            Name::new("Ludwig", "van", "Beethoven", "")
+        );
+        assert_eq!(
+            Name::parse("Ford, Jr., Henry"),
+            Name::new("Henry", "", "Ford", "Jr.")
+        );
+        assert_eq!(
+            Name::parse("de la Vall{\\'e}e Poussin, Jr, Charles"),
+            Name::new("Charles", "de la", "Vall{\\'e}e Poussin", "Jr")
+        );
+        assert_eq!(
+            Name::parse("{von Neumann}, John"),
+            Name::new("John", "", "{von Neumann}", "")
+        );
+    }
+
+    #[test]
+    fn test_parse_names() {
+        assert_eq!(
+            parse_names("Michel Goossens and Frank Mittelbach AND Alexander Samarin"),
+            vec![
+                Name::new("Michel", "", "Goossens", ""),
+                Name::new("Frank", "", "Mittelbach", ""),
+                Name::new("Alexander", "", "Samarin", ""),
+            ]
+        );
+        assert_eq!(
+            parse_names("{Barnes and Noble, Inc.} and Sandy Anderson"),
+            vec![
+                Name::new("", "", "{Barnes and Noble, Inc.}", ""),
+                Name::new("Sandy", "", "Anderson", ""),
+            ]
+        );
+        assert_eq!(
+            parse_names("Alexander Anderson"),
+            vec![Name::new("Alexander", "", "Anderson", "")]
+        );
+        let names = parse_names("Knuth, Donald and others");
+        assert_eq!(names.len(), 2);
+        assert!(names[1].is_others());
+        assert!(parse_names("").is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        for name in &[
+            "Ludwig van Beethoven",
+            "Ford, Jr., Henry",
+            "{Barnes and Noble}",
+            "de la Fontaine, Jean",
+        ] {
+            let parsed = Name::parse(name);
+            assert_eq!(Name::parse(&parsed.to_string()), parsed);
+        }
+        assert_eq!(
+            Name::parse("Ford, Jr., Henry").to_string(),
+            "Ford, Jr., Henry"
+        );
+    }
+}
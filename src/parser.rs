@@ -1,371 +1,1006 @@
-// //! In this module reside all the parsers need for the bibtex format.
-// //!
-// //! All the parsers are using the *nom* crates.
-//
-// // Required because the compiler don't seems do recognize
-// // that macros are use inside of each others..
-// #![allow(dead_code)]
-//
-use model::{KeyValue, StringValueType};
-use nom::types::CompleteByteSlice;
-use nom::{alpha, is_digit, Err, ErrorKind, IResult};
+//! In this module reside all the parsers need for the bibtex format.
+//!
+//! All the parsers are using the *nom* crates.
+
+use cst::{self, Body, Field, Piece, Value};
+use error::Expected;
+use model::{CommentStyle, Delimiters, KeyValue, StringValueType};
+use nom::branch::alt;
+use nom::bytes::complete::take_while1;
+use nom::character::complete::{alpha1, char, multispace0, multispace1};
+use nom::character::is_digit;
+use nom::combinator::{cond, map, map_res, opt, peek, recognize, value as constant};
+use nom::error::{context, ContextError, ErrorKind, FromExternalError, ParseError};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::Err;
 use std::str;
 
+/// The result of the parsers over the bytes of a *BibTeX* file content.
+pub type IResult<'a, O> = nom::IResult<&'a [u8], O, Error<'a>>;
+
+/// The error of the parsers: the remaining input where it occurred, what
+/// was expected there, the parts of the input being parsed, from the
+/// innermost to the outermost, and the key of the entry where it occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error<'a> {
+    pub input: &'a [u8],
+    pub expected: Expected,
+    pub context: Vec<&'static str>,
+    pub key: Option<&'a str>,
+}
+
+impl<'a> Error<'a> {
+    pub fn new(input: &'a [u8], expected: Expected) -> Self {
+        Error {
+            input,
+            expected,
+            context: vec![],
+            key: None,
+        }
+    }
+}
+
+impl<'a> ParseError<&'a [u8]> for Error<'a> {
+    fn from_error_kind(input: &'a [u8], _: ErrorKind) -> Self {
+        Error::new(input, Expected::Other)
+    }
+
+    // The innermost error is the most precise one.
+    fn append(_: &'a [u8], _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a> ContextError<&'a [u8]> for Error<'a> {
+    // The outer contexts are added after the inner ones.
+    fn add_context(_: &'a [u8], context: &'static str, mut other: Self) -> Self {
+        other.context.push(context);
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<&'a [u8], E> for Error<'a> {
+    fn from_external_error(input: &'a [u8], _: ErrorKind, _: E) -> Self {
+        Error::new(input, Expected::Other)
+    }
+}
+
+/// Build a recoverable error, which lets the alternatives be tried.
+fn error<'a>(input: &'a [u8], expected: Expected) -> Err<Error<'a>> {
+    Err::Error(Error::new(input, expected))
+}
+
+/// Build an error stopping the parsing.
+fn failure<'a>(input: &'a [u8], expected: Expected) -> Err<Error<'a>> {
+    Err::Failure(Error::new(input, expected))
+}
+
+/// Turn a recoverable error of the child parser into a failure telling what
+/// was expected, so that the error is not hidden by the alternatives tried
+/// afterwards.
+fn expect<'a, O, F>(expected: Expected, mut parser: F) -> impl FnMut(&'a [u8]) -> IResult<'a, O>
+where
+    F: FnMut(&'a [u8]) -> IResult<'a, O>,
+{
+    move |input| match parser(input) {
+        Err(Err::Error(_)) => Err(failure(input, expected)),
+        result => result,
+    }
+}
+
+/// Skip the whitespaces around the child parser.
+fn ws<'a, O, F>(parser: F) -> impl FnMut(&'a [u8]) -> IResult<'a, O>
+where
+    F: FnMut(&'a [u8]) -> IResult<'a, O>,
+{
+    delimited(multispace0, parser, multispace0)
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Entry<'a> {
     Preamble(Vec<StringValueType<'a>>),
     Comment(&'a str),
     Variable(KeyValue<'a>),
-    Bibliography(&'a str, &'a str, Vec<KeyValue<'a>>),
+    Bibliography(&'a str, &'a str, Vec<KeyValue<'a>>, Delimiters),
+}
+
+/// A top level part of the input, as parsed before being lowered to an
+/// `Entry` by `entries` or kept as is in a `cst::Document`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<'a> {
+    /// The whitespaces between the other nodes.
+    Whitespace,
+    /// A comment which is not a `@comment{...}` group, such as the text
+    /// between the entries, with its trimmed content.
+    Comment(&'a str),
+    /// An entry starting with a `@`.
+    Entry(cst::Entry<'a>),
+}
+
+/// Parse all the nodes of the input, which cover it entirely, along with the
+/// slice of the input they come from.
+pub fn nodes<'a>(input: &'a [u8], comments: CommentStyle) -> IResult<'a, Vec<(&'a str, Node<'a>)>> {
+    let mut nodes = vec![];
+    let mut rest = input;
+    loop {
+        let (remaining, node) = sourced_node(rest, comments)?;
+        nodes.push(node);
+        rest = remaining;
+        if rest.is_empty() {
+            break;
+        }
+    }
+    // There must be something else than whitespaces.
+    if nodes.iter().all(|node| node.1 == Node::Whitespace) {
+        return Err(failure(input, Expected::Entry));
+    }
+    Ok((rest, nodes))
 }
 
-named!(pub entries<CompleteByteSlice, Vec<Entry>>,
-    many1!(entry)
-);
+/// Parse all the entries along with the slice of the input they come from.
+pub fn entries<'a>(
+    input: &'a [u8],
+    comments: CommentStyle,
+) -> IResult<'a, Vec<(&'a str, Entry<'a>)>> {
+    let (rest, nodes) = nodes(input, comments)?;
+    let entries = nodes
+        .into_iter()
+        .filter_map(|(source, node)| lower(source, node))
+        .collect();
+    Ok((rest, entries))
+}
 
-/// Parse any entry in a bibtex file.
+/// Parse all the entries like `entries` but when an entry is malformed,
+/// keep its error and skip to the next `@` starting an entry.
+pub fn entries_lenient<'a>(
+    input: &'a [u8],
+    comments: CommentStyle,
+) -> (Vec<(&'a str, Entry<'a>)>, Vec<Err<Error<'a>>>) {
+    let mut entries = vec![];
+    let mut errors = vec![];
+    let mut input = input;
+    loop {
+        let start = input
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+            .unwrap_or(input.len());
+        input = &input[start..];
+        if input.is_empty() {
+            return (entries, errors);
+        }
+
+        match sourced_node(input, comments) {
+            Ok((rest, (source, node))) => {
+                entries.extend(lower(source, node));
+                input = rest;
+            }
+            Err(e) => {
+                errors.push(e);
+                let next = (1..input.len())
+                    .find(|&i| is_entry_start(input, i))
+                    .unwrap_or(input.len());
+                input = &input[next..];
+            }
+        }
+    }
+}
+
+/// Parse a node along with the slice of the input it comes from. The errors
+/// hold the key of the entry starting the node, if any.
+fn sourced_node<'a>(input: &'a [u8], comments: CommentStyle) -> IResult<'a, (&'a str, Node<'a>)> {
+    let result = match with_source(input, |input| node(input, comments)) {
+        Err(Err::Error(_)) => Err(failure(input, Expected::Entry)),
+        result => result,
+    };
+    result.map_err(|e| {
+        e.map(|e| Error {
+            key: entry_key(input),
+            ..e
+        })
+    })
+}
+
+/// Parse any node in a bibtex file.
 /// A good entry normally starts with a @ otherwise, it's
 /// considered as a comment.
-named!(pub entry<CompleteByteSlice, Entry>,
-    ws!(
-        alt!(
-            do_parse!(
-                peek!(char!('@')) >>
-                entry: entry_with_type >>
-                (entry)
-            ) | do_parse!(
-                comment: no_type_comment >>
-                (Entry::Comment(comment))
-            )
-        )
-    )
-);
-
-/// Handle data beginning without an @ which are considered comments.
-named!(no_type_comment<CompleteByteSlice, &str>,
-    map!(
-        map_res!(
-            is_not!("@"),
-            complete_byte_slice_to_str
+fn node<'a>(input: &'a [u8], comments: CommentStyle) -> IResult<'a, Node<'a>> {
+    alt((
+        constant(Node::Whitespace, multispace1),
+        preceded(peek(char('@')), |input| entry_with_type(input, comments)),
+        map(|input| no_type_comment(input, comments), Node::Comment),
+    ))(input)
+}
+
+/// Lower a node to the entry it holds, if any.
+fn lower<'a>(source: &'a str, node: Node<'a>) -> Option<(&'a str, Entry<'a>)> {
+    let entry = match node {
+        Node::Whitespace => return None,
+        Node::Comment(comment) => return Some((source.trim(), Entry::Comment(comment))),
+        Node::Entry(entry) => entry,
+    };
+    let lowered = match entry.body {
+        Body::Comment(ref comment) => Entry::Comment(part_of(source, comment).trim()),
+        Body::Preamble { ref value, .. } => Entry::Preamble(lower_value(source, value)),
+        Body::String { ref field, .. } => Entry::Variable(lower_field(source, field)),
+        Body::Bibliography {
+            ref citation_key,
+            ref fields,
+            ..
+        } => Entry::Bibliography(
+            part_of(source, &entry.entry_type),
+            part_of(source, citation_key),
+            fields
+                .iter()
+                .map(|field| lower_field(source, field))
+                .collect(),
+            entry.delimiters,
         ),
-        str::trim
+    };
+    Some((source, lowered))
+}
+
+fn lower_field<'a>(source: &'a str, field: &Field) -> KeyValue<'a> {
+    KeyValue::with_source(
+        part_of(source, &field.name),
+        lower_value(source, &field.value),
+        value_source(source, &field.value),
     )
-);
+}
+
+fn lower_value<'a>(source: &'a str, value: &Value) -> Vec<StringValueType<'a>> {
+    value
+        .pieces
+        .iter()
+        .map(|(_, piece)| match *piece {
+            Piece::Braced(ref content) => StringValueType::Str(part_of(source, content).trim()),
+            Piece::Quoted(ref content) | Piece::Raw(ref content) => {
+                StringValueType::Str(part_of(source, content))
+            }
+            Piece::Number(ref number) => StringValueType::Number(part_of(source, number)),
+            Piece::Name(ref name) => StringValueType::Abbreviation(part_of(source, name)),
+        })
+        .collect()
+}
+
+/// Get the part of `source` covering a value, with its delimiters.
+fn value_source<'a>(source: &'a str, value: &Value) -> &'a str {
+    let offset = |text: &str| text.as_ptr() as usize - source.as_ptr() as usize;
+    let bounds = |piece: &Piece| match *piece {
+        Piece::Braced(ref content) | Piece::Quoted(ref content) => {
+            (offset(content) - 1, offset(content) + content.len() + 1)
+        }
+        Piece::Number(ref content) | Piece::Name(ref content) | Piece::Raw(ref content) => {
+            (offset(content), offset(content) + content.len())
+        }
+    };
+    let (start, _) = bounds(&value.pieces[0].1);
+    let (_, end) = bounds(&value.pieces[value.pieces.len() - 1].1);
+    &source[start..end]
+}
+
+/// Get a text borrowed from `source` with the lifetime of `source`. The nodes
+/// built by the parsers only borrow the input.
+fn part_of<'a>(source: &'a str, text: &str) -> &'a str {
+    let start = text.as_ptr() as usize - source.as_ptr() as usize;
+    &source[start..start + text.len()]
+}
+
+/// Handle data which doesn't begin with an entry type as a comment, up to
+/// the next `@` starting an entry. An `@` which isn't followed by an entry
+/// type and its opening delimiter is a part of the comment.
+fn no_type_comment<'a>(input: &'a [u8], comments: CommentStyle) -> IResult<'a, &'a str> {
+    if input.is_empty() {
+        return Err(error(input, Expected::Entry));
+    }
+    let end = (1..input.len())
+        .find(|&i| is_entry_start(input, i) && has_entry_type(&input[i..], comments))
+        .unwrap_or(input.len());
+    match str::from_utf8(&input[..end]) {
+        Ok(comment) => Ok((&input[end..], comment.trim())),
+        Err(_) => Err(error(input, Expected::Other)),
+    }
+}
+
+/// Check if the `@` at `i` starts an entry. An `@` inside a word, such as in
+/// an email address, is only a part of the text between the entries.
+fn is_entry_start(input: &[u8], i: usize) -> bool {
+    input[i] == b'@' && (i == 0 || !is_word_char(input[i - 1]))
+}
+
+/// Check if the input starts with an entry type and its opening delimiter,
+/// or with a line comment in the *BibTeX* style.
+fn has_entry_type(input: &[u8], comments: CommentStyle) -> bool {
+    entry_type(input).is_ok() || (comments == CommentStyle::Bibtex && line_comment(input).is_ok())
+}
+
+fn is_word_char(c: u8) -> bool {
+    // The bytes of the non ASCII characters are considered as letters.
+    c.is_ascii_alphanumeric() || !c.is_ascii() || b"._-+".contains(&c)
+}
+
+/// Apply a parser and also return the part of the input it consumed.
+fn with_source<'a, O, F>(input: &'a [u8], mut parser: F) -> IResult<'a, (&'a str, O)>
+where
+    F: FnMut(&'a [u8]) -> IResult<'a, O>,
+{
+    let (rest, output) = parser(input)?;
+    match str::from_utf8(&input[..input.len() - rest.len()]) {
+        Ok(source) => Ok((rest, (source, output))),
+        Err(_) => Err(error(input, Expected::Other)),
+    }
+}
 
-fn complete_byte_slice_to_str<'a>(s: CompleteByteSlice<'a>) -> Result<&'a str, str::Utf8Error> {
-    str::from_utf8(s.0)
+/// Apply a parser and return the part of the input it consumed instead of
+/// its output.
+fn recognized<'a, O, F>(parser: F) -> impl FnMut(&'a [u8]) -> IResult<'a, &'a str>
+where
+    F: FnMut(&'a [u8]) -> IResult<'a, O>,
+{
+    map_res(recognize(parser), str::from_utf8)
+}
+
+/// Parse the whitespaces kept between the parts of an entry.
+fn space<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
+    recognized(multispace0)(input)
 }
 
 /// Parse any entry which starts with a @.
-fn entry_with_type<'a>(input: CompleteByteSlice<'a>) -> IResult<CompleteByteSlice<'a>, Entry> {
-    let entry_type = peeked_entry_type(input).unwrap();
+fn entry_with_type<'a>(input: &'a [u8], comments: CommentStyle) -> IResult<'a, Node<'a>> {
+    if comments == CommentStyle::Bibtex {
+        if let Ok((rest, comment)) = line_comment(input) {
+            return Ok((rest, Node::Comment(comment)));
+        }
+    }
+
+    // Without an entry type and its opening delimiter, the `@` is a part of
+    // a comment.
+    let entry_type = match peek(entry_type)(input) {
+        Ok((_, entry_type)) => entry_type,
+        Err(_) => return Err(error(&input[1..], Expected::EntryType)),
+    };
 
-    match entry_type.1.to_lowercase().as_ref() {
-        "comment" => type_comment(input),
-        "string" => variable(input),
-        "preamble" => preamble(input),
-        _ => bibliography_entry(input),
+    let entry = match entry_type.to_lowercase().as_ref() {
+        "comment" => context("a comment", type_comment)(input),
+        "string" => context("a string variable", variable)(input),
+        "preamble" => context("a preamble", preamble)(input),
+        _ => context("a bibliography entry", bibliography_entry)(input),
+    };
+    entry.map(|(rest, entry)| (rest, Node::Entry(entry)))
+}
+
+/// Build an entry from its head, as parsed by `entry_head`.
+fn new_entry<'a>(
+    (before_type, entry_type, after_type): (&'a str, &'a str, &'a str),
+    delimiters: Delimiters,
+    body: Body<'a>,
+) -> cst::Entry<'a> {
+    cst::Entry {
+        before_type: before_type.into(),
+        entry_type: entry_type.into(),
+        after_type: after_type.into(),
+        delimiters,
+        body,
     }
 }
 
 /// Handle a comment of the format:
-/// @Comment { my comment }
-named!(type_comment<CompleteByteSlice, Entry>, do_parse!(
-    entry_type >>
-    comment: call!(bracketed_string) >>
-    (Entry::Comment(comment))
-));
+/// @Comment { my comment } or @Comment ( my comment )
+fn type_comment<'a>(input: &'a [u8]) -> IResult<'a, cst::Entry<'a>> {
+    let (input, head) = entry_head(input)?;
+    let (input, (delimiters, comment)) = expect(
+        Expected::Comment,
+        alt((
+            map(bracketed_string, |comment| (Delimiters::Braces, comment)),
+            map(parenthesized_string, |comment| {
+                (Delimiters::Parentheses, comment)
+            }),
+        )),
+    )(input)?;
+    Ok((
+        input,
+        new_entry(head, delimiters, Body::Comment(comment.into())),
+    ))
+}
+
+/// Handle a comment of classic *BibTeX*, which is the rest of the line:
+/// @Comment my comment
+fn line_comment<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
+    let name_start = input
+        .iter()
+        .skip(1)
+        .position(|&c| c != b' ' && c != b'\t')
+        .map_or(input.len(), |i| i + 1);
+    let name_end = input[name_start..]
+        .iter()
+        .position(|c| !c.is_ascii_alphabetic())
+        .map_or(input.len(), |i| name_start + i);
+    if input.first() != Some(&b'@') || !input[name_start..name_end].eq_ignore_ascii_case(b"comment")
+    {
+        return Err(error(input, Expected::Comment));
+    }
+
+    let end = input[name_end..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .map_or(input.len(), |i| name_end + i);
+    match str::from_utf8(&input[name_end..end]) {
+        Ok(comment) => Ok((&input[end..], comment.trim())),
+        Err(_) => Err(error(input, Expected::Other)),
+    }
+}
 
 /// Handle a preamble of the format:
+/// @Preamble { "my preamble" } or @Preamble ( "my preamble" )
+///
+/// For compatibility, a preamble which isn't a value is taken as raw text:
 /// @Preamble { my preamble }
-named!(preamble<CompleteByteSlice, Entry>, do_parse!(
-    entry_type >>
-    ws!(char!('{')) >>
-    preamble: alt!(
-        // Required because otherwise the `mixed_abbreviation_string` will match for
-        // a single value like there were only one variable.
-        do_parse!(
-            val: call!(abbreviation_string) >>
-            peek!(ws!(char!('}'))) >>
-            (val)
-        ) |
-        map!(
-            map!(map_res!(take_until!("}"), complete_byte_slice_to_str), str::trim),
-            |v| vec![StringValueType::Str(v)]
-        )
-    ) >>
-    ws!(char!('}')) >>
-    (Entry::Preamble(preamble))
-));
+fn preamble<'a>(input: &'a [u8]) -> IResult<'a, cst::Entry<'a>> {
+    let (input, head) = entry_head(input)?;
+    let (input, delimiters) = expect(Expected::OpeningDelimiter, opening_delimiter)(input)?;
+    let (input, before) = space(input)?;
+    let (input, value) = expect(Expected::Value, |input| preamble_value(input, delimiters))(input)?;
+    let (input, end) = closing_delimiter(input, delimiters)?;
+    let body = Body::Preamble {
+        before: before.into(),
+        value,
+        end: end.into(),
+    };
+    Ok((input, new_entry(head, delimiters, body)))
+}
+
+fn preamble_value<'a>(input: &'a [u8], delimiters: Delimiters) -> IResult<'a, Value<'a>> {
+    alt((
+        terminated(value, peek(ws(char(delimiters.close())))),
+        map(
+            |input| raw_preamble(input, delimiters),
+            |raw| Piece::Raw(raw.into()).into(),
+        ),
+        // Only reached without a closing delimiter, to report it after the value.
+        value,
+    ))(input)
+}
+
+/// Take the text up to the closing delimiter of a preamble, without
+/// consuming the whitespaces before the delimiter. The braces of the text
+/// must be balanced.
+fn raw_preamble<'a>(input: &'a [u8], delimiters: Delimiters) -> IResult<'a, &'a str> {
+    let close = delimiters.close() as u8;
+    let mut depth = 0;
+    for (i, &c) in input.iter().enumerate() {
+        match c {
+            c if c == close && depth == 0 => {
+                return match str::from_utf8(&input[..i]) {
+                    Ok(raw) => {
+                        let raw = raw.trim_end();
+                        Ok((&input[raw.len()..], raw))
+                    }
+                    Err(_) => Err(error(input, Expected::Other)),
+                };
+            }
+            b'{' => depth += 1,
+            b'}' if depth == 0 => break,
+            b'}' => depth -= 1,
+            _ => {}
+        }
+    }
+    Err(error(input, Expected::Value))
+}
 
 /// Handle a string variable from the bibtex format:
 /// @String (key = "value") or @String {key = "value"}
-named!(variable<CompleteByteSlice, Entry>, do_parse!(
-    entry_type >>
-    key_val: call!(handle_variable) >>
-    alt!(char!('}') | char!(')')) >>
-    (Entry::Variable(key_val))
-));
-
-/// String variable can be delimited by brackets or parenthesis.
-named!(handle_variable<CompleteByteSlice, KeyValue>,
-    ws!(
-        alt!(
-           delimited!(
-               char!('{'),
-               call!(variable_key_value_pair),
-               peek!(char!('}'))
-           ) | delimited!(
-               char!('('),
-               call!(variable_key_value_pair),
-               peek!(char!(')'))
-           )
-        )
-    )
-);
+fn variable<'a>(input: &'a [u8]) -> IResult<'a, cst::Entry<'a>> {
+    let (input, head) = entry_head(input)?;
+    let (input, delimiters) = expect(Expected::OpeningDelimiter, opening_delimiter)(input)?;
+    let (input, before) = space(input)?;
+    let (input, field) = expect(Expected::Name, |input| field(input, before))(input)?;
+    let (input, end) = closing_delimiter(input, delimiters)?;
+    let body = Body::String {
+        field,
+        end: end.into(),
+    };
+    Ok((input, new_entry(head, delimiters, body)))
+}
 
-/// Parse key value pair which has the form:
+/// Parse a field which has the form:
 /// key="value"
-named!(variable_key_value_pair<CompleteByteSlice, KeyValue>,
-    map!(
-        separated_pair!(
-            map_res!(call!(alpha), complete_byte_slice_to_str),
-            ws!(char!('=')),
-            alt_complete!(
-                map!(call!(quoted_string), |v| vec![StringValueType::Str(v)]) |
-                call!(abbreviation_string) |
-                call!(abbreviation_only)
-            )
-        ),
-        |v: (&str, Vec<StringValueType<'_>>)| KeyValue::new(v.0, v.1)
-    )
-);
+///
+/// `before` is the text preceding it.
+fn field<'a>(input: &'a [u8], before: &'a str) -> IResult<'a, Field<'a>> {
+    let (input, name) = identifier(input)?;
+    let (input, equals) = expect(Expected::Equals, recognized(ws(char('='))))(input)?;
+    let (input, value) = expect(Expected::Value, value)(input)?;
+    Ok((
+        input,
+        Field {
+            before: before.into(),
+            name: name.into(),
+            equals: equals.into(),
+            value,
+        },
+    ))
+}
 
 /// Handle a bibliography entry of the format:
 /// @entry_type { citation_key,
 ///     tag1,
 ///     tag2
 /// }
-named!(pub bibliography_entry<CompleteByteSlice, Entry>, do_parse!(
-    entry_t: entry_type >>
-    ws!(char!('{')) >>
-    citation_key: ws!(map_res!(take_until_and_consume!(","), complete_byte_slice_to_str)) >>
-    tags: bib_tags >>
-    opt!(ws!(tag!(","))) >>
-    ws!(char!('}')) >>
-    (Entry::Bibliography(entry_t, citation_key, tags))
-));
-
-/// Parse all the tags used by one bibliography entry separated by a comma.
-named!(bib_tags<CompleteByteSlice, Vec<KeyValue<'_>>>,
-    separated_list!(
-        ws!(char!(',')),
-        map!(
-            separated_pair!(
-                // The key.
-                map_res!(call!(alpha), complete_byte_slice_to_str),
-                ws!(char!('=')),
-                alt_complete!(
-                    call!(abbreviation_string) |
-                    map!(call!(quoted_string), |v| vec![StringValueType::Str(v)]) |
-                    map!(call!(bracketed_string), |v| vec![StringValueType::Str(v)]) |
-                    map!(
-                        map_res!(take_while1!(is_digit), complete_byte_slice_to_str),
-                        |v| vec![StringValueType::Str(v)]
-                    ) |
-                    call!(abbreviation_only)
-                )
-            ),
-            |v: (&str, Vec<StringValueType<'_>>)| KeyValue::new(v.0, v.1)
-        )
-    )
-);
+fn bibliography_entry<'a>(input: &'a [u8]) -> IResult<'a, cst::Entry<'a>> {
+    let (input, head) = entry_head(input)?;
+    let (input, delimiters) = expect(Expected::OpeningDelimiter, opening_delimiter)(input)?;
+    let (input, before) = space(input)?;
+    let (input, citation_key) = citation_key(input, delimiters)?;
+    let (input, fields) = bib_fields(input)?;
+    // Without any field, the citation key can be followed by two commas.
+    let (input, end) = recognized(tuple((
+        opt(ws(char(','))),
+        cond(fields.is_empty(), opt(ws(char(',')))),
+        |input| closing_delimiter(input, delimiters),
+    )))(input)?;
+    let body = Body::Bibliography {
+        before: before.into(),
+        citation_key: citation_key.into(),
+        fields,
+        // Without the closing delimiter.
+        end: end[..end.len() - 1].into(),
+    };
+    Ok((input, new_entry(head, delimiters, body)))
+}
+
+/// Parse the citation key of a bibliography entry, which is followed by a
+/// `,` or by the end of an entry without any tag.
+fn citation_key<'a>(input: &'a [u8], delimiters: Delimiters) -> IResult<'a, &'a str> {
+    let skip_whitespace = |from: usize| {
+        input[from..]
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+            .map_or(input.len(), |i| from + i)
+    };
+    let start = skip_whitespace(0);
+    let end = input[start..]
+        .iter()
+        .position(|&c| !is_key_char(c, delimiters))
+        .map_or(input.len(), |i| start + i);
+    if start == end {
+        return Err(failure(&input[start..], Expected::CitationKey));
+    }
+    let next = skip_whitespace(end);
+    match input.get(next) {
+        Some(&b',') => {}
+        Some(&c) if c == delimiters.close() as u8 => {}
+        _ => return Err(failure(&input[next..], Expected::CitationKey)),
+    }
+    match str::from_utf8(&input[start..end]) {
+        Ok(key) => Ok((&input[end..], key)),
+        Err(_) => Err(error(input, Expected::Other)),
+    }
+}
+
+/// Check if a byte can be a part of a citation key. Like in *biber*, a key
+/// can contain any character except whitespaces, braces and `,`. The closing
+/// delimiter of the entry is also excluded.
+pub fn is_key_char(c: u8, delimiters: Delimiters) -> bool {
+    !c.is_ascii_whitespace() && !b",{}".contains(&c) && c != delimiters.close() as u8
+}
+
+/// Check if a whole citation key can be parsed back in an entry enclosed in
+/// `delimiters`.
+pub fn is_citation_key(key: &str, delimiters: Delimiters) -> bool {
+    !key.is_empty() && key.bytes().all(|c| is_key_char(c, delimiters))
+}
+
+/// Parse the `{` or `(` opening the body of an entry.
+fn opening_delimiter<'a>(input: &'a [u8]) -> IResult<'a, Delimiters> {
+    alt((
+        constant(Delimiters::Braces, char('{')),
+        constant(Delimiters::Parentheses, char('(')),
+    ))(input)
+}
+
+/// Parse the `}` or `)` closing the body of an entry opened with
+/// `delimiters`, and return the whitespaces before it.
+fn closing_delimiter<'a>(input: &'a [u8], delimiters: Delimiters) -> IResult<'a, &'a str> {
+    let expected = match delimiters {
+        Delimiters::Braces => Expected::ClosingBrace,
+        Delimiters::Parentheses => Expected::ClosingParenthesis,
+    };
+    expect(expected, terminated(space, char(delimiters.close())))(input)
+}
+
+/// Parse all the fields of one bibliography entry, each preceded by a comma.
+fn bib_fields<'a>(input: &'a [u8]) -> IResult<'a, Vec<Field<'a>>> {
+    let mut fields = vec![];
+    let mut input = input;
+    loop {
+        let result = recognized(ws(char(',')))(input)
+            .and_then(|(rest, before)| context("a field", |input| field(input, before))(rest));
+        match result {
+            Ok((rest, field)) => {
+                fields.push(field);
+                input = rest;
+            }
+            Err(Err::Error(_)) => return Ok((input, fields)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Parse the value of a tag, a string variable or a preamble: braced
+/// strings, quoted strings, numbers and abbreviations in any order,
+/// concatenated with `#`.
+fn value<'a>(input: &'a [u8]) -> IResult<'a, Value<'a>> {
+    let (mut input, first) = piece(input)?;
+    let mut pieces = vec![("".into(), first)];
+    loop {
+        match pair(recognized(ws(char('#'))), piece)(input) {
+            Ok((rest, (before, piece))) => {
+                pieces.push((before.into(), piece));
+                input = rest;
+            }
+            Err(Err::Error(_)) => return Ok((input, Value { pieces })),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn piece<'a>(input: &'a [u8]) -> IResult<'a, Piece<'a>> {
+    alt((
+        map(bracketed_string, |content| Piece::Braced(content.into())),
+        map(quoted_string, |content| Piece::Quoted(content.into())),
+        map(number, |number| Piece::Number(number.into())),
+        map(identifier, |name| Piece::Name(name.into())),
+    ))(input)
+}
+
+fn number<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
+    map_res(take_while1(is_digit), str::from_utf8)(input)
+}
+
+/// Parse the `@` and the type of an entry, along with the whitespaces
+/// around the type.
+fn entry_head<'a>(input: &'a [u8]) -> IResult<'a, (&'a str, &'a str, &'a str)> {
+    preceded(
+        char('@'),
+        tuple((space, map_res(alpha1, str::from_utf8), space)),
+    )(input)
+}
 
 /// Parse a bibtex entry type which looks like:
 /// @type{ ...
 ///
 /// But don't consume the last bracket.
-named!(entry_type<CompleteByteSlice, &str>,
-    delimited!(
-        char!('@'),
-        map_res!(ws!(alpha), complete_byte_slice_to_str),
-        peek!(
-            alt!(
-                char!('{') |
+fn entry_type<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
+    map(
+        terminated(
+            entry_head,
+            peek(alt((
+                char('{'),
                 // Handling for variable string.
-                char!('(')
-            )
-        )
+                char('('),
+            ))),
+        ),
+        |(_, entry_type, _)| entry_type,
+    )(input)
+}
 
-    )
-);
+/// Get the citation key or the string variable name of the entry starting
+/// the input, even if it's followed by something else than a `,`. It's only
+/// used to give some context to the errors.
+fn entry_key(input: &[u8]) -> Option<&str> {
+    let (input, (_, entry_type, _)) = entry_head(input).ok()?;
+    let (input, delimiters) = opening_delimiter(input).ok()?;
+    let (input, _) = space(input).ok()?;
+    match entry_type.to_lowercase().as_ref() {
+        "comment" | "preamble" => None,
+        "string" => identifier(input).ok().map(|(_, name)| name),
+        _ => {
+            let (_, key) =
+                take_while1::<_, _, Error>(|c| is_key_char(c, delimiters))(input).ok()?;
+            str::from_utf8(key).ok()
+        }
+    }
+}
 
-/// Same as entry_type but with peek so it doesn't consume the
-/// entry type.
-named!(peeked_entry_type<CompleteByteSlice, &str>,
-    peek!(
-        entry_type
-    )
-);
-
-named!(abbreviation_string<CompleteByteSlice, Vec<StringValueType>>,
-    complete!(separated_nonempty_list!(
-        ws!(char!('#')),
-        alt!(
-            map!(map_res!(call!(alpha), complete_byte_slice_to_str), |v| StringValueType::Abbreviation(v)) |
-            map!(call!(quoted_string), |v| StringValueType::Str(v))
-        )
-    ))
-);
+/// Parse an identifier used for tag names, string variables names and
+/// abbreviations.
+///
+/// Like in *BibTeX*, it's made of any printable characters except whitespaces
+/// and `"#%'(),={}` and it can't start with a digit. `@` is also excluded so
+/// that an identifier never runs into the next entry.
+fn identifier<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
+    let end = input
+        .iter()
+        .position(|&c| !is_identifier_char(c))
+        .unwrap_or(input.len());
+    if end == 0 || is_digit(input[0]) {
+        return Err(error(input, Expected::Name));
+    }
+    match str::from_utf8(&input[..end]) {
+        Ok(id) => Ok((&input[end..], id)),
+        Err(_) => Err(error(input, Expected::Other)),
+    }
+}
 
-named!(abbreviation_only<CompleteByteSlice, Vec<StringValueType>>,
-    ws!(map!(map_res!(call!(alpha), complete_byte_slice_to_str), |v| vec![StringValueType::Abbreviation(v)]))
-);
+pub fn is_identifier_char(c: u8) -> bool {
+    !c.is_ascii_whitespace() && !c.is_ascii_control() && !b"\"#%'(),={}@".contains(&c)
+}
 
-/// Only used for bibliography tags.
-fn bracketed_string<'a>(input: CompleteByteSlice<'a>) -> IResult<CompleteByteSlice<'a>, &str> {
-    let input = &input;
+/// Check if a whole tag or string variable name can be parsed back.
+pub fn is_identifier(name: &str) -> bool {
+    identifier(name.as_bytes()).is_ok_and(|(rest, _)| rest.is_empty())
+}
+
+/// Check if an entry type can be parsed back as the type of a bibliography,
+/// which excludes the types of the special entries.
+pub fn is_bibliography_type(entry_type: &str) -> bool {
+    !entry_type.is_empty()
+        && entry_type.bytes().all(|c| c.is_ascii_alphabetic())
+        && !["comment", "preamble", "string"]
+            .iter()
+            .any(|special| entry_type.eq_ignore_ascii_case(special))
+}
+
+fn bracketed_string<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
     // We are not in a bracketed_string.
-    if input[0] as char != '{' {
-        return Err(Err::Error(error_position!(
-            CompleteByteSlice(input),
-            ErrorKind::Custom(0)
-        )));
+    if input.first() != Some(&b'{') {
+        return Err(error(input, Expected::Value));
     }
     let mut brackets_queue = 0;
 
-    let mut i = 0;
-    loop {
-        i += 1;
-        match input[i] as char {
-            '{' => brackets_queue += 1,
-            '}' => if brackets_queue == 0 {
-                break;
-            } else {
-                brackets_queue -= 1;
-            },
-            '"' => if brackets_queue == 0 {
-                return Err(Err::Error(error_position!(
-                    CompleteByteSlice(input),
-                    ErrorKind::Custom(0)
-                )));
-            },
-            '@' => {
-                return Err(Err::Error(error_position!(
-                    CompleteByteSlice(input),
-                    ErrorKind::Custom(0)
-                )))
+    for (i, &c) in input.iter().enumerate().skip(1) {
+        match c {
+            b'{' => brackets_queue += 1,
+            b'}' => {
+                if brackets_queue == 0 {
+                    return str_value(input, i);
+                } else {
+                    brackets_queue -= 1;
+                }
             }
             _ => continue,
         }
     }
-    let value = str::from_utf8(&input[1..i]).expect("Unable to parse char sequence");
-    Ok((CompleteByteSlice(&input[i + 1..]), str::trim(value)))
+    Err(failure(input, Expected::MatchingBrace))
 }
 
-fn quoted_string<'a>(input: CompleteByteSlice<'a>) -> IResult<CompleteByteSlice<'a>, &str> {
-    let input = input.as_ref();
-    if input[0] as char != '"' {
-        return Err(Err::Error(error_position!(
-            CompleteByteSlice(input),
-            ErrorKind::Custom(0)
-        )));
+/// Only used for the comments enclosed in parentheses.
+fn parenthesized_string<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
+    if input.first() != Some(&b'(') {
+        return Err(error(input, Expected::Comment));
+    }
+    let mut depth = 0;
+    for (i, &c) in input.iter().enumerate().skip(1) {
+        match c {
+            b')' if depth == 0 => {
+                return str_value(input, i);
+            }
+            b'{' => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            // The braces must be balanced inside the parentheses.
+            b'}' => return Err(error(input, Expected::Comment)),
+            _ => {}
+        }
+    }
+    Err(failure(input, Expected::ClosingParenthesis))
+}
+
+fn quoted_string<'a>(input: &'a [u8]) -> IResult<'a, &'a str> {
+    if input.first() != Some(&b'"') {
+        return Err(error(input, Expected::Value));
     }
 
     let mut brackets_queue = 0;
-    let mut i = 0;
-    loop {
-        i += 1;
-        match input[i] as char {
-            '{' => brackets_queue += 1,
-            '}' => {
+    for (i, &c) in input.iter().enumerate().skip(1) {
+        match c {
+            b'{' => brackets_queue += 1,
+            b'}' => {
                 brackets_queue -= 1;
                 if brackets_queue < 0 {
-                    return Err(Err::Error(error_position!(
-                        CompleteByteSlice(input),
-                        ErrorKind::Custom(0)
-                    )));
+                    return Err(error(input, Expected::Value));
+                }
+            }
+            b'"' => {
+                if brackets_queue == 0 {
+                    return str_value(input, i);
                 }
             }
-            '"' => if brackets_queue == 0 {
-                break;
-            },
             _ => continue,
         }
     }
-    let value = str::from_utf8(&input[1..i]).expect("Unable to parse char sequence");
-    Ok((CompleteByteSlice(&input[i + 1..]), value))
+    Err(failure(input, Expected::MatchingQuote))
+}
+
+/// Split a delimited value whose closing delimiter is at `end`.
+fn str_value<'a>(input: &'a [u8], end: usize) -> IResult<'a, &'a str> {
+    match str::from_utf8(&input[1..end]) {
+        Ok(value) => Ok((&input[end + 1..], value)),
+        Err(_) => Err(failure(input, Expected::Other)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    // Each time we are using `separated_list`, we need to add a trailing
-    // character otherwise the parser will return `IResult::Incomplete`.
-    // Relevant nom issue: https://github.com/Geal/nom/issues/505
-
     use super::*;
 
+    /// Lower the node returned by a parser on `input`.
+    fn lowered<'a, F>(input: &'a [u8], mut parser: F) -> IResult<'a, Entry<'a>>
+    where
+        F: FnMut(&'a [u8]) -> IResult<'a, Node<'a>>,
+    {
+        let (rest, node) = parser(input)?;
+        let source = str::from_utf8(input).unwrap();
+        Ok((rest, lower(source, node).unwrap().1))
+    }
+
     #[test]
-    fn test_entry() {
+    fn test_error() {
         assert_eq!(
-            entry(CompleteByteSlice(b" comment")),
-            Ok((CompleteByteSlice(b""), Entry::Comment("comment")))
+            char::<_, Error>('{')(b"("),
+            Err(Err::Error(Error::new(b"(", Expected::Other)))
+        );
+        assert_eq!(
+            expect(Expected::OpeningDelimiter, opening_delimiter)(b" ["),
+            Err(Err::Failure(Error::new(b" [", Expected::OpeningDelimiter)))
+        );
+        assert_eq!(number(b"12a"), Ok((&b"a"[..], "12")));
+    }
+
+    #[test]
+    fn test_node() {
+        let biber = CommentStyle::Biber;
+        assert_eq!(
+            node(b" \n@misc{", biber),
+            Ok((&b"@misc{"[..], Node::Whitespace))
+        );
+        assert_eq!(
+            node(b"comment\n@misc{", biber),
+            Ok((&b"@misc{"[..], Node::Comment("comment")))
         );
 
         let kv = KeyValue::new("key", vec![StringValueType::Str("value")]);
         assert_eq!(
-            entry(CompleteByteSlice(b" @ StrIng { key = \"value\" }")),
-            Ok((CompleteByteSlice(b""), Entry::Variable(kv)))
+            lowered(b"@ StrIng { key = \"value\" } ", |input| node(input, biber)),
+            Ok((&b" "[..], Entry::Variable(kv)))
         );
+    }
 
-        let bib_str = b"@misc{ patashnik-bibtexing,
-           author = \"Oren Patashnik\",
-           title = \"BIBTEXing\",
-           year = \"1988\" }";
+    #[test]
+    fn test_nodes() {
+        let input = " text\n@misc{ key, a = 1 }\n";
+        let (_, parsed) = nodes(input.as_bytes(), CommentStyle::Biber).unwrap();
+        let sources = parsed.iter().map(|node| node.0).collect::<String>();
+        assert_eq!(sources, input);
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed[1], ("text\n", Node::Comment("text")));
 
-        let tags = vec![
-            KeyValue::new("author", vec![StringValueType::Str("Oren Patashnik")]),
-            KeyValue::new("title", vec![StringValueType::Str("BIBTEXing")]),
-            KeyValue::new("year", vec![StringValueType::Str("1988")]),
-        ];
-        assert_eq!(
-            entry_with_type(CompleteByteSlice(bib_str)),
-            Ok((
-                CompleteByteSlice(b""),
-                Entry::Bibliography("misc", "patashnik-bibtexing", tags)
-            ))
+        assert!(nodes(b" \n", CommentStyle::Biber).is_err());
+        assert!(nodes(b"", CommentStyle::Biber).is_err());
+    }
+
+    #[test]
+    fn test_entries() {
+        let (_, entries) = entries(
+            b" my comment
+            @string{ key = \"value\" }
+            @misc{ key, title = {A title} }",
+            CommentStyle::Biber,
+        )
+        .unwrap();
+
+        assert_eq!(entries[0], ("my comment", Entry::Comment("my comment")));
+        assert_eq!(entries[1].0, "@string{ key = \"value\" }");
+        assert_eq!(entries[2].0, "@misc{ key, title = {A title} }");
+        match entries[1].1 {
+            Entry::Variable(ref kv) => assert_eq!(kv.value_source, "\"value\""),
+            _ => panic!("Expected a variable."),
+        }
+        match entries[2].1 {
+            Entry::Bibliography(_, _, ref tags, _) => assert_eq!(tags[0].value_source, "{A title}"),
+            _ => panic!("Expected a bibliography."),
+        }
+    }
+
+    #[test]
+    fn test_entries_lenient() {
+        let (entries, errors) = entries_lenient(
+            b"@misc{ first, title = {First} }
+            @misc{ broken, title {Broken} }
+            @misc{ last, title = {Last} }
+            ",
+            CommentStyle::Biber,
         );
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "@misc{ first, title = {First} }");
+        assert_eq!(entries[1].0, "@misc{ last, title = {Last} }");
+        assert_eq!(errors.len(), 1);
     }
 
     #[test]
     fn test_no_type_comment() {
+        let biber = CommentStyle::Biber;
         assert_eq!(
-            no_type_comment(CompleteByteSlice(b"test@")),
-            Ok((CompleteByteSlice(b"@"), "test"))
+            no_type_comment(b"test @misc{", biber),
+            Ok((&b"@misc{"[..], "test"))
         );
+        assert_eq!(no_type_comment(b"test", biber), Ok((&b""[..], "test")));
         assert_eq!(
-            no_type_comment(CompleteByteSlice(b"test")),
-            Ok((CompleteByteSlice(b""), "test"))
+            no_type_comment(b"mail me@example.com\n@misc(", biber),
+            Ok((&b"@misc("[..], "mail me@example.com"))
         );
+        assert_eq!(
+            no_type_comment(b"junk @ here", biber),
+            Ok((&b""[..], "junk @ here"))
+        );
+        assert_eq!(
+            no_type_comment(b"@john for questions\n@misc{", biber),
+            Ok((&b"@misc{"[..], "@john for questions"))
+        );
+        assert_eq!(
+            no_type_comment(b"text @comment line", biber),
+            Ok((&b""[..], "text @comment line"))
+        );
+        assert_eq!(
+            no_type_comment(b"text @comment line", CommentStyle::Bibtex),
+            Ok((&b"@comment line"[..], "text"))
+        );
+        assert!(no_type_comment(b"", biber).is_err());
+    }
+
+    #[test]
+    fn test_is_entry_start() {
+        assert!(is_entry_start(b"@misc", 0));
+        assert!(is_entry_start(b"text\n@misc", 5));
+        assert!(is_entry_start(b"(@misc", 1));
+        assert!(!is_entry_start(b"a@b.org", 1));
+        assert!(!is_entry_start(b"a.@b", 2));
+        assert!(!is_entry_start("é@b".as_bytes(), 2));
+        assert!(!is_entry_start(b"text", 0));
+    }
+
+    #[test]
+    fn test_line_comment() {
+        assert_eq!(
+            line_comment(b"@comment{ text\n@misc"),
+            Ok((&b"\n@misc"[..], "{ text"))
+        );
+        assert_eq!(line_comment(b"@ COMMENT"), Ok((&b""[..], "")));
+        assert!(line_comment(b"@comments{text}").is_err());
+        assert!(line_comment(b"@misc{text}").is_err());
     }
 
     #[test]
     fn test_entry_with_type() {
         assert_eq!(
-            entry_with_type(CompleteByteSlice(b"@Comment{test}")),
-            Ok((CompleteByteSlice(b""), Entry::Comment("test")))
+            lowered(b"@Comment{test}", |input| entry_with_type(
+                input,
+                CommentStyle::Biber
+            )),
+            Ok((&b""[..], Entry::Comment("test")))
+        );
+        assert_eq!(
+            lowered(b"@Comment{test}", |input| entry_with_type(
+                input,
+                CommentStyle::Bibtex
+            )),
+            Ok((&b""[..], Entry::Comment("{test}")))
         );
 
         let kv = KeyValue::new("key", vec![StringValueType::Str("value")]);
         assert_eq!(
-            entry_with_type(CompleteByteSlice(b"@String{key=\"value\"}")),
-            Ok((CompleteByteSlice(b""), Entry::Variable(kv)))
+            lowered(b"@String{key=\"value\"}", |input| entry_with_type(
+                input,
+                CommentStyle::Biber
+            )),
+            Ok((&b""[..], Entry::Variable(kv)))
         );
 
         assert_eq!(
-            entry_with_type(CompleteByteSlice(b"@preamble{name # \"'s preamble\"}")),
+            lowered(b"@preamble{name # \"'s preamble\"}", |input| {
+                entry_with_type(input, CommentStyle::Biber)
+            }),
             Ok((
-                CompleteByteSlice(b""),
+                &b""[..],
                 Entry::Preamble(vec![
                     StringValueType::Abbreviation("name"),
                     StringValueType::Str("'s preamble")
@@ -384,10 +1019,10 @@ mod tests {
             KeyValue::new("year", vec![StringValueType::Str("1988")]),
         ];
         assert_eq!(
-            entry_with_type(CompleteByteSlice(bib_str)),
+            lowered(bib_str, |input| entry_with_type(input, CommentStyle::Biber)),
             Ok((
-                CompleteByteSlice(b""),
-                Entry::Bibliography("misc", "patashnik-bibtexing", tags)
+                &b""[..],
+                Entry::Bibliography("misc", "patashnik-bibtexing", tags, Delimiters::Braces)
             ))
         );
     }
@@ -395,20 +1030,82 @@ mod tests {
     #[test]
     fn test_type_comment() {
         assert_eq!(
-            type_comment(CompleteByteSlice(b"@Comment{test}")),
-            Ok((CompleteByteSlice(b""), Entry::Comment("test")))
+            lowered(b"@Comment{test}", map(type_comment, Node::Entry)),
+            Ok((&b""[..], Entry::Comment("test")))
+        );
+        assert_eq!(
+            lowered(b"@Comment( {a)} (b )", map(type_comment, Node::Entry)),
+            Ok((&b""[..], Entry::Comment("{a)} (b")))
+        );
+        assert!(type_comment(b"@Comment( a } )").is_err());
+        assert_eq!(
+            type_comment(b"@Comment( test"),
+            Err(Err::Failure(Error::new(
+                b"( test",
+                Expected::ClosingParenthesis
+            )))
         );
     }
 
     #[test]
     fn test_preamble() {
         assert_eq!(
-            preamble(CompleteByteSlice(b"@preamble{my preamble}")),
+            lowered(b"@preamble{my preamble}", map(preamble, Node::Entry)),
             Ok((
-                CompleteByteSlice(b""),
+                &b""[..],
                 Entry::Preamble(vec![StringValueType::Str("my preamble")])
             ))
         );
+        assert_eq!(
+            lowered(
+                b"@preamble{ {a} # \"b\" # name # 1 }",
+                map(preamble, Node::Entry)
+            ),
+            Ok((
+                &b""[..],
+                Entry::Preamble(vec![
+                    StringValueType::Str("a"),
+                    StringValueType::Str("b"),
+                    StringValueType::Abbreviation("name"),
+                    StringValueType::Number("1"),
+                ])
+            ))
+        );
+        assert_eq!(
+            lowered(
+                b"@preamble{\\newcommand{\\noop}[1]{#1}}",
+                map(preamble, Node::Entry)
+            ),
+            Ok((
+                &b""[..],
+                Entry::Preamble(vec![StringValueType::Str("\\newcommand{\\noop}[1]{#1}")])
+            ))
+        );
+        assert_eq!(
+            lowered(b"@preamble( \"a\" # {b)} )", map(preamble, Node::Entry)),
+            Ok((
+                &b""[..],
+                Entry::Preamble(vec![StringValueType::Str("a"), StringValueType::Str("b)")])
+            ))
+        );
+        assert_eq!(
+            lowered(b"@preamble(my {preamble})", map(preamble, Node::Entry)),
+            Ok((
+                &b""[..],
+                Entry::Preamble(vec![StringValueType::Str("my {preamble}")])
+            ))
+        );
+        assert_eq!(
+            preamble(b"@preamble( \"a\" }"),
+            Err(Err::Failure(Error::new(
+                b" }",
+                Expected::ClosingParenthesis
+            )))
+        );
+        assert_eq!(
+            preamble(b"@preamble{ \"a\" "),
+            Err(Err::Failure(Error::new(b" ", Expected::ClosingBrace)))
+        );
     }
 
     #[test]
@@ -424,35 +1121,51 @@ mod tests {
         );
 
         assert_eq!(
-            variable(CompleteByteSlice(b"@string{key=\"value\"}")),
-            Ok((CompleteByteSlice(b""), Entry::Variable(kv1)))
+            lowered(b"@string{key=\"value\"}", map(variable, Node::Entry)),
+            Ok((&b""[..], Entry::Variable(kv1)))
         );
 
         assert_eq!(
-            variable(CompleteByteSlice(b"@string( key=\"value\" )")),
-            Ok((CompleteByteSlice(b""), Entry::Variable(kv2)))
+            lowered(b"@string( key=\"value\" )", map(variable, Node::Entry)),
+            Ok((&b""[..], Entry::Variable(kv2)))
         );
 
         assert_eq!(
-            variable(CompleteByteSlice(b"@string( key=varone # vartwo)")),
-            Ok((CompleteByteSlice(b""), Entry::Variable(kv3)))
+            lowered(b"@string( key=varone # vartwo)", map(variable, Node::Entry)),
+            Ok((&b""[..], Entry::Variable(kv3)))
         );
-    }
 
-    #[test]
-    fn test_variable_key_value_pair() {
-        let kv = KeyValue::new(
+        let kv4 = KeyValue::new(
             "key",
             vec![
-                StringValueType::Abbreviation("varone"),
-                StringValueType::Abbreviation("vartwo"),
+                StringValueType::Str("braced"),
+                StringValueType::Number("2018"),
             ],
         );
+        assert_eq!(
+            lowered(
+                b"@string{key = {braced} # 2018}",
+                map(variable, Node::Entry)
+            ),
+            Ok((&b""[..], Entry::Variable(kv4)))
+        );
+    }
 
+    #[test]
+    fn test_field() {
+        use self::StringValueType::*;
+
+        let input = "key = varone # vartwo,";
+        let (rest, field) = field(input.as_bytes(), " ").unwrap();
+        assert_eq!(rest, &b","[..]);
+        assert_eq!(field.before, " ");
+        assert_eq!(field.name, "key");
+        assert_eq!(field.equals, " = ");
         assert_eq!(
-            variable_key_value_pair(CompleteByteSlice(b"key = varone # vartwo,")),
-            Ok((CompleteByteSlice(b","), kv))
+            lower_value(input, &field.value),
+            vec![Abbreviation("varone"), Abbreviation("vartwo")]
         );
+        assert_eq!(value_source(input, &field.value), "varone # vartwo");
     }
 
     #[test]
@@ -468,24 +1181,99 @@ mod tests {
             KeyValue::new("year", vec![StringValueType::Str("1988")]),
         ];
         assert_eq!(
-            bibliography_entry(CompleteByteSlice(bib_str)),
+            lowered(bib_str, map(bibliography_entry, Node::Entry)),
+            Ok((
+                &b""[..],
+                Entry::Bibliography("misc", "patashnik-bibtexing", tags, Delimiters::Braces)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_bibliography_entry_parentheses() {
+        let tags = vec![
+            KeyValue::new("title", vec![StringValueType::Str("A (title)")]),
+            KeyValue::new("note", vec![StringValueType::Str("b)")]),
+        ];
+        assert_eq!(
+            lowered(
+                b"@misc( key, title = \"A (title)\", note = {b)} )",
+                map(bibliography_entry, Node::Entry)
+            ),
+            Ok((
+                &b""[..],
+                Entry::Bibliography("misc", "key", tags, Delimiters::Parentheses)
+            ))
+        );
+        assert_eq!(
+            bibliography_entry(b"@misc( key, title = {A} }"),
+            Err(Err::Failure(Error::new(
+                b" }",
+                Expected::ClosingParenthesis
+            )))
+        );
+        assert_eq!(
+            bibliography_entry(b"@misc{ key, title = {A} )"),
+            Err(Err::Failure(Error::new(b" )", Expected::ClosingBrace)))
+        );
+    }
+
+    #[test]
+    fn test_citation_key() {
+        assert_eq!(
+            citation_key(b" doi:10.1000/a+b.c ,", Delimiters::Braces),
+            Ok((&b" ,"[..], "doi:10.1000/a+b.c"))
+        );
+        assert_eq!(
+            citation_key("Ångström2018}".as_bytes(), Delimiters::Braces),
+            Ok((&b"}"[..], "Ångström2018"))
+        );
+        assert_eq!(
+            citation_key(b"key)", Delimiters::Parentheses),
+            Ok((&b")"[..], "key"))
+        );
+        assert_eq!(
+            citation_key(b"my key, title = {A}", Delimiters::Braces),
+            Err(Err::Failure(Error::new(
+                b"key, title = {A}",
+                Expected::CitationKey
+            )))
+        );
+        assert_eq!(
+            citation_key(b"a{b}, title = {A}", Delimiters::Braces),
+            Err(Err::Failure(Error::new(
+                b"{b}, title = {A}",
+                Expected::CitationKey
+            )))
+        );
+        assert_eq!(
+            citation_key(b" , title = {A}", Delimiters::Braces),
+            Err(Err::Failure(Error::new(
+                b", title = {A}",
+                Expected::CitationKey
+            )))
+        );
+        assert_eq!(
+            lowered(
+                b"@misc{ key }@misc{other}",
+                map(bibliography_entry, Node::Entry)
+            ),
             Ok((
-                CompleteByteSlice(b""),
-                Entry::Bibliography("misc", "patashnik-bibtexing", tags)
+                &b"@misc{other}"[..],
+                Entry::Bibliography("misc", "key", vec![], Delimiters::Braces)
             ))
         );
     }
 
     #[test]
-    fn test_bib_tags() {
-        let tags_str = b"author= \"Oren Patashnik\",
+    fn test_bib_fields() {
+        let input = ",\n            author= \"Oren Patashnik\",
             year=1988,
             note= var # \"str\",
             title= { My new book }}";
-
         let result = vec![
             KeyValue::new("author", vec![StringValueType::Str("Oren Patashnik")]),
-            KeyValue::new("year", vec![StringValueType::Str("1988")]),
+            KeyValue::new("year", vec![StringValueType::Number("1988")]),
             KeyValue::new(
                 "note",
                 vec![
@@ -495,109 +1283,176 @@ mod tests {
             ),
             KeyValue::new("title", vec![StringValueType::Str("My new book")]),
         ];
+        let (rest, fields) = bib_fields(input.as_bytes()).unwrap();
+        assert_eq!(rest, &b"}"[..]);
+        assert_eq!(fields[0].before, ",\n            ");
         assert_eq!(
-            bib_tags(CompleteByteSlice(tags_str)),
-            Ok((CompleteByteSlice(b"}"), result))
+            fields
+                .iter()
+                .map(|field| lower_field(input, field))
+                .collect::<Vec<_>>(),
+            result
         );
+        assert_eq!(bib_fields(b", }"), Ok((&b", }"[..], vec![])));
     }
 
     #[test]
     fn test_entry_type() {
-        assert_eq!(
-            entry_type(CompleteByteSlice(b"@misc{")),
-            Ok((CompleteByteSlice(b"{"), "misc"))
-        );
+        assert_eq!(entry_type(b"@misc{"), Ok((&b"{"[..], "misc")));
 
-        assert_eq!(
-            entry_type(CompleteByteSlice(b"@ misc {")),
-            Ok((CompleteByteSlice(b"{"), "misc"))
-        );
+        assert_eq!(entry_type(b"@ misc {"), Ok((&b"{"[..], "misc")));
 
-        assert_eq!(
-            entry_type(CompleteByteSlice(b"@string(")),
-            Ok((CompleteByteSlice(b"("), "string"))
-        );
+        assert_eq!(entry_type(b"@string("), Ok((&b"("[..], "string")));
     }
 
     #[test]
-    fn test_abbreviation_string() {
-        assert_eq!(
-            abbreviation_string(CompleteByteSlice(b"var # \"string\",")),
-            Ok((
-                CompleteByteSlice(b","),
+    fn test_value() {
+        use self::StringValueType::*;
+
+        let values: Vec<(&[u8], Vec<StringValueType>)> = vec![
+            (b"{braced},", vec![Str("braced")]),
+            (b"\"quoted\",", vec![Str("quoted")]),
+            (b"2018,", vec![Number("2018")]),
+            (b"var,", vec![Abbreviation("var")]),
+            (
+                b"var # \"string\",",
+                vec![Abbreviation("var"), Str("string")],
+            ),
+            (
+                b"\"string\" # var,",
+                vec![Str("string"), Abbreviation("var")],
+            ),
+            (
+                b"string # var,",
+                vec![Abbreviation("string"), Abbreviation("var")],
+            ),
+            (b"{a} # \"b\",", vec![Str("a"), Str("b")]),
+            (b"\"a\" # {b},", vec![Str("a"), Str("b")]),
+            (b"{a}#{b},", vec![Str("a"), Str("b")]),
+            (b"var # {b},", vec![Abbreviation("var"), Str("b")]),
+            (b"{a} # var,", vec![Str("a"), Abbreviation("var")]),
+            (b"1 # 2,", vec![Number("1"), Number("2")]),
+            (b"jan # 2020,", vec![Abbreviation("jan"), Number("2020")]),
+            (b"2020 # {a},", vec![Number("2020"), Str("a")]),
+            (b"\"a\" # 12,", vec![Str("a"), Number("12")]),
+            (
+                b"var\n # {b}\t#\"c\" # 4 # d,",
                 vec![
-                    StringValueType::Abbreviation("var"),
-                    StringValueType::Str("string"),
-                ]
-            ))
+                    Abbreviation("var"),
+                    Str("b"),
+                    Str("c"),
+                    Number("4"),
+                    Abbreviation("d"),
+                ],
+            ),
+            (b"{A \"quoted\" word},", vec![Str("A \"quoted\" word")]),
+        ];
+        for (input, expected) in values {
+            let source = str::from_utf8(input).unwrap();
+            assert_eq!(
+                value(input).map(|(rest, value)| (rest, lower_value(source, &value))),
+                Ok((&b","[..], expected)),
+                "{}",
+                str::from_utf8(input).unwrap()
+            );
+        }
+
+        assert_eq!(
+            value(b"a # ,"),
+            Ok((&b" # ,"[..], Piece::Name("a".into()).into()))
         );
+        assert!(value(b"# a").is_err());
+        assert!(value(b",").is_err());
+    }
+
+    #[test]
+    fn test_raw_preamble() {
         assert_eq!(
-            abbreviation_string(CompleteByteSlice(b"\"string\" # var,")),
-            Ok((
-                CompleteByteSlice(b","),
-                vec![
-                    StringValueType::Str("string"),
-                    StringValueType::Abbreviation("var"),
-                ]
-            ))
+            raw_preamble(b"raw {nested} text }", Delimiters::Braces),
+            Ok((&b" }"[..], "raw {nested} text"))
         );
         assert_eq!(
-            abbreviation_string(CompleteByteSlice(b"string # var,")),
-            Ok((
-                CompleteByteSlice(b","),
-                vec![
-                    StringValueType::Abbreviation("string"),
-                    StringValueType::Abbreviation("var"),
-                ]
-            ))
+            raw_preamble(b"raw {)} text)", Delimiters::Parentheses),
+            Ok((&b")"[..], "raw {)} text"))
         );
+        assert!(raw_preamble(b"raw {text}", Delimiters::Braces).is_err());
+        assert!(raw_preamble(b"raw } text)", Delimiters::Parentheses).is_err());
+    }
+
+    #[test]
+    fn test_entry_key() {
+        assert_eq!(entry_key(b"@misc{ a=b(c), title = {A}}"), Some("a=b(c)"));
+        assert_eq!(entry_key(b"@misc(key)"), Some("key"));
+        assert_eq!(entry_key(b"@string{ name = {A}}"), Some("name"));
+        assert_eq!(entry_key(b"@preamble{name}"), None);
+        assert_eq!(entry_key(b"@misc{my key, title = {A}}"), Some("my"));
+        assert_eq!(entry_key(b"@misc{, title = {A}}"), None);
+        assert_eq!(entry_key(b"@b.c}"), None);
     }
 
     #[test]
-    fn test_abbreviation_only() {
+    fn test_identifier() {
+        assert_eq!(identifier(b"bdsk-url-1 ="), Ok((&b" ="[..], "bdsk-url-1")));
+        assert_eq!(identifier(b"isbn_13="), Ok((&b"="[..], "isbn_13")));
         assert_eq!(
-            abbreviation_only(CompleteByteSlice(b" var ")),
-            Ok((
-                CompleteByteSlice(b""),
-                vec![StringValueType::Abbreviation("var")]
-            ))
+            identifier(b"conf:icml.2020 # "),
+            Ok((&b" # "[..], "conf:icml.2020"))
         );
+        assert_eq!(identifier("résumé}".as_bytes()), Ok((&b"}"[..], "résumé")));
+        assert!(identifier(b"2020jan").is_err());
+        assert!(identifier(b"{title}").is_err());
+        assert!(identifier(b"").is_err());
+
+        assert!(is_identifier("bdsk-url-1"));
+        assert!(!is_identifier("my field"));
+        assert!(!is_identifier("2020jan"));
+        assert!(!is_identifier(""));
+    }
+
+    #[test]
+    fn test_is_bibliography_type() {
+        assert!(is_bibliography_type("inProceedings"));
+        assert!(!is_bibliography_type("online-2"));
+        assert!(!is_bibliography_type("String"));
+        assert!(!is_bibliography_type("comment"));
+        assert!(!is_bibliography_type(""));
     }
 
     #[test]
     fn test_bracketed_string() {
+        assert_eq!(bracketed_string(b"{ test }"), Ok((&b""[..], " test ")));
+        assert_eq!(bracketed_string(b"{{test}}"), Ok((&b""[..], "{test}")));
+        assert_eq!(bracketed_string(b"{@{test}}"), Ok((&b""[..], "@{test}")));
+        assert!(bracketed_string(b"").is_err());
         assert_eq!(
-            bracketed_string(CompleteByteSlice(b"{ test }")),
-            Ok((CompleteByteSlice(b""), "test"))
-        );
-        assert_eq!(
-            bracketed_string(CompleteByteSlice(b"{ {test} }")),
-            Ok((CompleteByteSlice(b""), "{test}"))
+            bracketed_string(b"{ {test}"),
+            Err(Err::Failure(Error::new(
+                b"{ {test}",
+                Expected::MatchingBrace
+            )))
         );
-        assert!(bracketed_string(CompleteByteSlice(b"{ @{test} }")).is_err());
     }
 
     #[test]
     fn test_quoted_string() {
+        assert_eq!(quoted_string(b"\"test\""), Ok((&b""[..], "test")));
+        assert_eq!(quoted_string(b"\"test \""), Ok((&b""[..], "test ")));
         assert_eq!(
-            quoted_string(CompleteByteSlice(b"\"test\"")),
-            Ok((CompleteByteSlice(b""), "test"))
-        );
-        assert_eq!(
-            quoted_string(CompleteByteSlice(b"\"test \"")),
-            Ok((CompleteByteSlice(b""), "test "))
+            quoted_string(b"\"{\"test\"}\""),
+            Ok((&b""[..], "{\"test\"}"))
         );
         assert_eq!(
-            quoted_string(CompleteByteSlice(b"\"{\"test\"}\"")),
-            Ok((CompleteByteSlice(b""), "{\"test\"}"))
+            quoted_string(b"\"A {bunch {of} braces {in}} title\""),
+            Ok((&b""[..], "A {bunch {of} braces {in}} title"))
         );
         assert_eq!(
-            quoted_string(CompleteByteSlice(b"\"A {bunch {of} braces {in}} title\"")),
-            Ok((CompleteByteSlice(b""), "A {bunch {of} braces {in}} title"))
+            quoted_string(b"\"Simon {\"}the {saint\"} Templar\""),
+            Ok((&b""[..], "Simon {\"}the {saint\"} Templar"))
         );
+        assert!(quoted_string(b"").is_err());
         assert_eq!(
-            quoted_string(CompleteByteSlice(b"\"Simon {\"}the {saint\"} Templar\"")),
-            Ok((CompleteByteSlice(b""), "Simon {\"}the {saint\"} Templar"))
+            quoted_string(b"\"test"),
+            Err(Err::Failure(Error::new(b"\"test", Expected::MatchingQuote)))
         );
     }
 }
@@ -0,0 +1,656 @@
+//! Read and write RIS, the tagged format exported by the publishers and the
+//! reference managers.
+//!
+//! A RIS record starts with a `TY` tag giving its type and ends with an `ER`
+//! tag, each line holding a tag such as `TY  - JOUR`. A line without a tag
+//! continues the value of the previous line.
+//!
+//! The values are decoded from *LaTeX* to Unicode when written, and the
+//! special characters are escaped when read, see the `latex` module. The
+//! `doi` and `url` fields are kept verbatim.
+//!
+//! ## Mapping
+//!
+//! | BibTeX                                 | RIS                        |
+//! |----------------------------------------|----------------------------|
+//! | `@article`                             | `JOUR`                     |
+//! | `@book`                                | `BOOK`                     |
+//! | `@booklet`                             | `PAMP`                     |
+//! | `@inbook`, `@incollection`             | `CHAP`                     |
+//! | `@inproceedings`, `@conference`        | `CPAPER`                   |
+//! | `@proceedings`                         | `CONF`                     |
+//! | `@manual`, `@techreport`, `@report`    | `RPRT`                     |
+//! | `@mastersthesis`, `@phdthesis`         | `THES`                     |
+//! | `@unpublished`                         | `UNPB`                     |
+//! | `@online`                              | `ELEC`                     |
+//! | other types                            | `GEN`                      |
+//! | citation key                           | `ID`                       |
+//! | `author`                               | `AU` (`A1`)                |
+//! | `editor`                               | `ED` (`A2`)                |
+//! | `title`                                | `TI` (`T1`)                |
+//! | `journal`                              | `JO` (`JF`, `T2`, `JA`)    |
+//! | `booktitle`                            | `T2` (`BT`)                |
+//! | `series`                               | `T3`                       |
+//! | `year` and `month`                     | `PY` (`Y1`, `DA`)          |
+//! | `volume`, `number`                     | `VL`, `IS`                 |
+//! | `pages` (`12--34`)                     | `SP` (`12`) and `EP` (`34`)|
+//! | `publisher`, `school`, `institution`   | `PB`                       |
+//! | `address`                              | `CY`                       |
+//! | `edition`                              | `ET`                       |
+//! | `isbn`, `issn`                         | `SN`                       |
+//! | `doi`, `url`                           | `DO`, `UR`                 |
+//! | `abstract`, `note`                     | `AB` (`N2`), `N1`          |
+//! | `keywords`                             | `KW`, one per keyword      |
+//! | `language`                             | `LA`                       |
+//!
+//! The tags in parentheses are only read. When reading, the other RIS types
+//! are `@misc` entries, except the `EJOUR`, `MGZN` and `NEWS` articles, the
+//! `EBOOK` and `EDBOOK` books and the `ECHAP` chapters. The `PB` tag is the
+//! `school` of a thesis and the `institution` of a report, the `SN` tag is
+//! the `isbn` of a book or a chapter and the `issn` otherwise. A record
+//! without an `ID` tag is given a citation key made of the last name of its
+//! first author and of its year, such as `smith2018`, followed by a letter
+//! if this key is already used. The other tags are not converted.
+//!
+//! ## Example
+//!
+//! ```
+//! use nom_bibtex::ris;
+//!
+//! let bibliographies = ris::read("TY  - JOUR
+//! AU  - Gödel, Kurt
+//! TI  - Über formal unentscheidbare Sätze
+//! PY  - 1931
+//! SP  - 173
+//! EP  - 198
+//! ER  - ").unwrap();
+//! assert_eq!(bibliographies[0].entry_type(), "article");
+//! assert_eq!(bibliographies[0].citation_key(), "godel1931");
+//! assert_eq!(bibliographies[0].get("pages"), Some("173--198"));
+//!
+//! assert!(ris::write(&bibliographies).starts_with("TY  - JOUR\nID  - godel1931\nAU  - Gödel, Kurt\n"));
+//! ```
+
+use latex::{decode, encode, Encoding};
+use model::{Bibliography, Delimiters};
+use names::Name;
+use parser;
+use std::collections::HashSet;
+
+quick_error! {
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum RisError {
+        TagOutsideRecord (line: usize, tag: String) {
+            description("RIS tag outside of a record.")
+            display("RIS tag outside of a record.: `{}` at line {}", tag, line)
+        }
+        InvalidLine (line: usize) {
+            description("Invalid RIS line.")
+            display("Invalid RIS line.: line {}", line)
+        }
+        UnterminatedRecord (line: usize) {
+            description("RIS record without an `ER` tag.")
+            display("RIS record without an `ER` tag.: record starting at line {}", line)
+        }
+        InvalidKey (line: usize, id: String) {
+            description("RIS `ID` which is not a valid citation key.")
+            display("RIS `ID` which is not a valid citation key.: `{}` in the record starting at line {}", id, line)
+        }
+    }
+}
+
+/// The entry types and the RIS types they are written to.
+const TYPES: &[(&str, &str)] = &[
+    ("article", "JOUR"),
+    ("book", "BOOK"),
+    ("booklet", "PAMP"),
+    ("inbook", "CHAP"),
+    ("incollection", "CHAP"),
+    ("inproceedings", "CPAPER"),
+    ("conference", "CPAPER"),
+    ("proceedings", "CONF"),
+    ("manual", "RPRT"),
+    ("techreport", "RPRT"),
+    ("report", "RPRT"),
+    ("mastersthesis", "THES"),
+    ("phdthesis", "THES"),
+    ("thesis", "THES"),
+    ("unpublished", "UNPB"),
+    ("online", "ELEC"),
+];
+
+/// The RIS types and the entry types they are read to.
+const RIS_TYPES: &[(&str, &str)] = &[
+    ("JOUR", "article"),
+    ("JFULL", "article"),
+    ("EJOUR", "article"),
+    ("MGZN", "article"),
+    ("NEWS", "article"),
+    ("BOOK", "book"),
+    ("EBOOK", "book"),
+    ("EDBOOK", "book"),
+    ("PAMP", "booklet"),
+    ("CHAP", "incollection"),
+    ("ECHAP", "incollection"),
+    ("CPAPER", "inproceedings"),
+    ("CONF", "proceedings"),
+    ("RPRT", "techreport"),
+    ("THES", "phdthesis"),
+    ("UNPB", "unpublished"),
+];
+
+/// The fields with a single tag, in the order they are written.
+const FIELDS: &[(&str, &str)] = &[
+    ("series", "T3"),
+    ("volume", "VL"),
+    ("number", "IS"),
+    ("address", "CY"),
+    ("edition", "ET"),
+    ("doi", "DO"),
+    ("url", "UR"),
+    ("abstract", "AB"),
+    ("note", "N1"),
+    ("language", "LA"),
+];
+
+/// The tags read to the fields with a single tag, the first tag found is
+/// used when several tags have the same field.
+const RIS_FIELDS: &[(&str, &str)] = &[
+    ("T3", "series"),
+    ("VL", "volume"),
+    ("IS", "number"),
+    ("CY", "address"),
+    ("ET", "edition"),
+    ("DO", "doi"),
+    ("UR", "url"),
+    ("AB", "abstract"),
+    ("N2", "abstract"),
+    ("N1", "note"),
+    ("LA", "language"),
+];
+
+/// The fields which are not *LaTeX*, so neither decoded nor encoded.
+const VERBATIM_FIELDS: &[&str] = &["doi", "url"];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A record being read, with its tags and values in their original order.
+struct Record {
+    ris_type: String,
+    line: usize,
+    tags: Vec<(String, String)>,
+}
+
+impl Record {
+    /// Get the first value of the first of the given tags found.
+    fn first(&self, tags: &[&str]) -> Option<&str> {
+        tags.iter().filter_map(|&tag| self.get(tag)).next()
+    }
+
+    fn get(&self, tag: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|t| t.0 == tag && !t.1.is_empty())
+            .map(|t| t.1.as_str())
+    }
+
+    /// Get all the values of the given tags.
+    fn all(&self, tags: &[&str]) -> Vec<&str> {
+        self.tags
+            .iter()
+            .filter(|t| tags.contains(&&*t.0) && !t.1.is_empty())
+            .map(|t| t.1.as_str())
+            .collect()
+    }
+}
+
+/// Read the bibliographies from a RIS content.
+pub fn read(content: &str) -> Result<Vec<Bibliography<'static>>, RisError> {
+    let mut records = vec![];
+    let mut record: Option<Record> = None;
+    let content = content.trim_start_matches('\u{feff}');
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim_end();
+        match (tag_line(line), record.as_mut()) {
+            (Some(("ER", _)), Some(_)) => records.extend(record.take()),
+            (Some((tag, value)), Some(record)) => record.tags.push((tag.into(), value.into())),
+            (Some(("TY", value)), None) => {
+                record = Some(Record {
+                    ris_type: value.to_uppercase(),
+                    line: line_number,
+                    tags: vec![],
+                })
+            }
+            (Some((tag, _)), None) => {
+                return Err(RisError::TagOutsideRecord(line_number, tag.into()));
+            }
+            (None, _) if line.trim().is_empty() => (),
+            (None, Some(record)) => match record.tags.last_mut() {
+                Some(last) => {
+                    if !last.1.is_empty() {
+                        last.1.push(' ');
+                    }
+                    last.1.push_str(line.trim());
+                }
+                None => return Err(RisError::InvalidLine(line_number)),
+            },
+            (None, None) => return Err(RisError::InvalidLine(line_number)),
+        }
+    }
+    if let Some(record) = record {
+        return Err(RisError::UnterminatedRecord(record.line));
+    }
+    for record in &records {
+        match record.get("ID") {
+            Some(id) if !parser::is_citation_key(id, Delimiters::Braces) => {
+                return Err(RisError::InvalidKey(record.line, id.into()));
+            }
+            _ => {}
+        }
+    }
+
+    let mut keys = HashSet::new();
+    Ok(records
+        .iter()
+        .map(|record| to_bibliography(record, &mut keys))
+        .collect())
+}
+
+/// Split a line such as `AU  - Smith, Jane` into its tag and its value.
+fn tag_line(line: &str) -> Option<(&str, &str)> {
+    let bytes = line.as_bytes();
+    let is_tag = bytes.len() >= 4
+        && bytes[..2]
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && bytes[2] == b' ';
+    if !is_tag {
+        return None;
+    }
+    line[2..]
+        .trim_start()
+        .strip_prefix('-')
+        .map(|value| (&line[..2], value.trim()))
+}
+
+fn to_bibliography(record: &Record, keys: &mut HashSet<String>) -> Bibliography<'static> {
+    let entry_type = RIS_TYPES
+        .iter()
+        .find(|t| t.0 == record.ris_type)
+        .map_or("misc", |t| t.1);
+    let mut tags: Vec<(String, String)> = vec![];
+    let latex = |value: &str| encode(value, Encoding::Minimal);
+
+    let authors = record.all(&["AU", "A1"]);
+    if !authors.is_empty() {
+        tags.push(("author".into(), bibtex_names(&authors)));
+    }
+    let editors = record.all(&["ED", "A2"]);
+    if !editors.is_empty() {
+        tags.push(("editor".into(), bibtex_names(&editors)));
+    }
+    if let Some(title) = record.first(&["TI", "T1"]) {
+        tags.push(("title".into(), latex(title)));
+    }
+    let container = if entry_type == "article" {
+        record
+            .first(&["JO", "JF", "T2", "JA"])
+            .map(|title| ("journal", title))
+    } else {
+        record
+            .first(&["T2", "BT"])
+            .map(|title| ("booktitle", title))
+    };
+    if let Some((field, title)) = container {
+        tags.push((field.into(), latex(title)));
+    }
+
+    let date = record.first(&["PY", "Y1", "DA"]).unwrap_or("");
+    let mut parts = date.split('/').map(str::trim);
+    if let Some(year) = parts.next().filter(|year| !year.is_empty()) {
+        tags.push(("year".into(), latex(year)));
+    }
+    let month = parts
+        .next()
+        .filter(|month| !month.is_empty())
+        .or_else(|| record.get("DA").and_then(|date| date.split('/').nth(1)))
+        .and_then(|month| month.trim().parse::<usize>().ok())
+        .and_then(|month| MONTHS.get(month.wrapping_sub(1)));
+    if let Some(month) = month {
+        tags.push(("month".into(), month.to_string()));
+    }
+
+    for &(tag, field) in RIS_FIELDS {
+        if let Some(value) = record.get(tag) {
+            if !tags.iter().any(|t| t.0 == field) {
+                let value = if VERBATIM_FIELDS.contains(&field) {
+                    value.to_string()
+                } else {
+                    latex(value)
+                };
+                tags.push((field.into(), value));
+            }
+        }
+    }
+
+    let pages = match (record.get("SP"), record.get("EP")) {
+        (Some(start), Some(end)) => Some(format!("{}--{}", start, end)),
+        (Some(start), None) => Some(start.replace(['-', '–'], "--").replace("----", "--")),
+        (None, Some(end)) => Some(end.to_string()),
+        (None, None) => None,
+    };
+    if let Some(pages) = pages {
+        tags.push(("pages".into(), latex(&pages)));
+    }
+    if let Some(publisher) = record.get("PB") {
+        let field = match entry_type {
+            "phdthesis" => "school",
+            "techreport" => "institution",
+            _ => "publisher",
+        };
+        tags.push((field.into(), latex(publisher)));
+    }
+    if let Some(number) = record.get("SN") {
+        let field = match entry_type {
+            "book" | "incollection" | "proceedings" => "isbn",
+            _ => "issn",
+        };
+        tags.push((field.into(), latex(number)));
+    }
+    let keywords = record.all(&["KW"]);
+    if !keywords.is_empty() {
+        tags.push(("keywords".into(), latex(&keywords.join(", "))));
+    }
+
+    let citation_key = match record.get("ID") {
+        Some(id) => id.to_string(),
+        None => generate_key(&authors, date, keys),
+    };
+    keys.insert(citation_key.to_lowercase());
+    Bibliography::new(entry_type, citation_key, tags)
+}
+
+/// Convert the RIS names, such as `Smith, Jane, Jr.`, to *BibTeX* names
+/// separated by `and`.
+fn bibtex_names(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| {
+            let mut parts = name
+                .split(',')
+                .map(|part| encode(part.trim(), Encoding::Minimal));
+            let last = parts.next().unwrap_or_default();
+            let first = parts.next().unwrap_or_default();
+            let jr = parts.next().unwrap_or_default();
+            let name = Name {
+                first,
+                von: String::new(),
+                last,
+                jr,
+            };
+            name.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Generate a citation key from the last name of the first author and the
+/// year, adding a letter if this key is already used.
+fn generate_key(authors: &[&str], date: &str, keys: &HashSet<String>) -> String {
+    let last = authors
+        .first()
+        .and_then(|author| author.split(',').next())
+        .unwrap_or("");
+    let mut key = last
+        .split_whitespace()
+        .last()
+        .map(|word| decode(word).text)
+        .unwrap_or_default()
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(ascii_letter)
+        .collect::<String>();
+    key.extend(date.chars().take_while(char::is_ascii_digit));
+    if key.is_empty() {
+        key.push_str("ris");
+    }
+    if !keys.contains(&key) {
+        return key;
+    }
+    (b'a'..=b'z')
+        .map(|suffix| format!("{}{}", key, suffix as char))
+        .chain((1..).map(|suffix| format!("{}-{}", key, suffix)))
+        .find(|key| !keys.contains(key))
+        .expect("An unused key exists")
+}
+
+/// Get the ASCII letter of a character, without its accent.
+fn ascii_letter(c: char) -> Option<char> {
+    if c.is_ascii_alphabetic() {
+        return Some(c);
+    }
+    match c {
+        'à'..='å' | 'ā' | 'ą' => Some('a'),
+        'ç' | 'ć' | 'č' => Some('c'),
+        'è'..='ë' | 'ē' | 'ę' | 'ě' => Some('e'),
+        'ì'..='ï' | 'ī' => Some('i'),
+        'ñ' | 'ń' | 'ň' => Some('n'),
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => Some('o'),
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' => Some('u'),
+        'ý' | 'ÿ' => Some('y'),
+        'ł' => Some('l'),
+        'ř' => Some('r'),
+        'ś' | 'š' | 'ß' => Some('s'),
+        'ź' | 'ż' | 'ž' => Some('z'),
+        _ => None,
+    }
+}
+
+/// Write the bibliographies as RIS records.
+///
+/// The cross-references should be resolved beforehand to write the
+/// inherited fields.
+pub fn write(bibliographies: &[Bibliography]) -> String {
+    bibliographies
+        .iter()
+        .map(write_bibliography)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Write a bibliography as a RIS record.
+pub fn write_bibliography(bibliography: &Bibliography) -> String {
+    let mut out = String::new();
+    let mut line = |tag: &str, value: &str| {
+        out.push_str(tag);
+        out.push_str("  - ");
+        out.push_str(value);
+        out.push('\n');
+    };
+    let text = |field: &str| {
+        bibliography
+            .get(field)
+            .map(|value| {
+                if VERBATIM_FIELDS.contains(&field) {
+                    value.to_string()
+                } else {
+                    decode(value).text
+                }
+            })
+            .filter(|value| !value.is_empty())
+    };
+    let entry_type = bibliography.entry_type().to_lowercase();
+
+    let ris_type = TYPES
+        .iter()
+        .find(|t| t.0 == entry_type)
+        .map_or("GEN", |t| t.1);
+    line("TY", ris_type);
+    line("ID", bibliography.citation_key());
+    for &(field, tag) in &[("author", "AU"), ("editor", "ED")] {
+        for name in bibliography.names(field) {
+            if !name.is_others() {
+                line(tag, &ris_name(&name));
+            }
+        }
+    }
+    if let Some(title) = text("title") {
+        line("TI", &title);
+    }
+    if let Some(title) = text("journal").or_else(|| text("journaltitle")) {
+        line("JO", &title);
+    }
+    if let Some(title) = text("booktitle") {
+        line("T2", &title);
+    }
+
+    let year = text("year").or_else(|| {
+        text("date").map(|date| date.chars().take_while(char::is_ascii_digit).collect())
+    });
+    if let Some(year) = year {
+        line("PY", &year);
+    }
+    let month = text("month").and_then(|month| month_number(&month));
+    if let (Some(year), Some(month)) = (text("year"), month) {
+        line("DA", &format!("{}/{:02}//", year, month));
+    } else if let Some(date) = text("date") {
+        line("DA", &date.replace('-', "/"));
+    }
+
+    for &(field, tag) in FIELDS {
+        if let Some(value) = text(field) {
+            line(tag, &value);
+        }
+    }
+    if let Some(pages) = text("pages") {
+        let mut pages = pages
+            .split(['-', '–', '—'])
+            .map(str::trim)
+            .filter(|page| !page.is_empty());
+        if let Some(start) = pages.next() {
+            line("SP", start);
+        }
+        if let Some(end) = pages.next_back() {
+            line("EP", end);
+        }
+    }
+    if let Some(publisher) = text("publisher")
+        .or_else(|| text("school"))
+        .or_else(|| text("institution"))
+    {
+        line("PB", &publisher);
+    }
+    if let Some(number) = text("isbn").or_else(|| text("issn")) {
+        line("SN", &number);
+    }
+    if let Some(keywords) = text("keywords") {
+        for keyword in keywords.split([',', ';']).map(str::trim) {
+            if !keyword.is_empty() {
+                line("KW", keyword);
+            }
+        }
+    }
+    line("ER", "");
+    out
+}
+
+/// Convert a name to a RIS name such as `van Beethoven, Ludwig`.
+fn ris_name(name: &Name) -> String {
+    let mut last = decode(&name.last).text;
+    if !name.von.is_empty() {
+        last = format!("{} {}", decode(&name.von).text, last);
+    }
+    let mut parts = vec![last];
+    if !name.first.is_empty() || !name.jr.is_empty() {
+        parts.push(decode(&name.first).text);
+    }
+    if !name.jr.is_empty() {
+        parts.push(decode(&name.jr).text);
+    }
+    parts.join(", ")
+}
+
+/// Get the number of a month given by its number or its name.
+fn month_number(month: &str) -> Option<usize> {
+    let month = month.trim().trim_end_matches('.').to_lowercase();
+    month
+        .parse()
+        .ok()
+        .filter(|m| (1..=12).contains(m))
+        .or_else(|| {
+            MONTHS
+                .iter()
+                .position(|m| {
+                    let m = m.to_lowercase();
+                    m == month || (month.len() == 3 && m.starts_with(&month))
+                })
+                .map(|i| i + 1)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_line() {
+        assert_eq!(tag_line("TY  - JOUR"), Some(("TY", "JOUR")));
+        assert_eq!(tag_line("ER  -"), Some(("ER", "")));
+        assert_eq!(tag_line("T2 - A title "), Some(("T2", "A title")));
+        assert_eq!(tag_line("AB  - A-B - C"), Some(("AB", "A-B - C")));
+        assert_eq!(tag_line("continued - text"), None);
+        assert_eq!(tag_line("ab  - text"), None);
+        assert_eq!(tag_line("TY  JOUR"), None);
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            bibtex_names(&["Smith, Jane", "King, Martin Luther, Jr.", "Plato"]),
+            "Smith, Jane and King, Jr., Martin Luther and Plato"
+        );
+        assert_eq!(bibtex_names(&["AT&T"]), "AT\\&T");
+        assert_eq!(
+            ris_name(&Name::parse("van Beethoven, Jr, Ludwig")),
+            "van Beethoven, Ludwig, Jr"
+        );
+        assert_eq!(ris_name(&Name::parse("{\\\"O}zt{\\\"u}rk")), "Öztürk");
+    }
+
+    #[test]
+    fn test_generate_key() {
+        let mut keys = HashSet::new();
+        assert_eq!(
+            generate_key(&["de la Peña, José"], "2018/03/21/", &keys),
+            "pena2018"
+        );
+        assert_eq!(generate_key(&[], "", &keys), "ris");
+        keys.insert("smith2018".to_string());
+        assert_eq!(generate_key(&["Smith, J."], "2018", &keys), "smith2018a");
+        keys.insert("smith2018a".to_string());
+        assert_eq!(generate_key(&["Smith, J."], "2018", &keys), "smith2018b");
+    }
+
+    #[test]
+    fn test_month_number() {
+        assert_eq!(month_number("jun"), Some(6));
+        assert_eq!(month_number("December"), Some(12));
+        assert_eq!(month_number("3"), Some(3));
+        assert_eq!(month_number("13"), None);
+        assert_eq!(month_number("spring"), None);
+    }
+}
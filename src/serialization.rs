@@ -0,0 +1,343 @@
+//! Support of *serde*, enabled with the `serde` feature.
+//!
+//! A `Bibtex` and a `Bibliography` are serialized with their content only:
+//! the comments, preambles, string variables and bibliographies, and the
+//! entry type, citation key and tags of each bibliography. Their locations
+//! in the input, the raw values and the delimiters are not serialized.
+//!
+//! A `&Bibliography` is also a `Deserializer` which maps the tags onto the
+//! fields of a struct:
+//!
+//! - the names of the tags are lowercase, `entry_type` and `citation_key`
+//!   are available too,
+//! - a number, such as an `u32` `year`, is parsed from the value,
+//! - a sequence of a name field, such as a `Vec<Name>` or a `Vec<String>`
+//!   `author`, is read from the names separated by `and`,
+//! - a sequence of another field, such as a `Vec<String>` `keywords`, is read
+//!   from the values separated by `,` or `;`,
+//! - a missing tag can be read as an `Option`.
+//!
+//! ```
+//! # extern crate nom_bibtex;
+//! # extern crate serde;
+//! use nom_bibtex::names::Name;
+//! use nom_bibtex::Bibtex;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Paper {
+//!     citation_key: String,
+//!     title: String,
+//!     year: u32,
+//!     author: Vec<Name>,
+//!     doi: Option<String>,
+//! }
+//!
+//! # fn main() {
+//! let bibtex = Bibtex::parse("@misc{key, title = {A title}, YEAR = 2018, author = {Knuth, Donald and Me}}").unwrap();
+//! let paper = Paper::deserialize(&bibtex.bibliographies()[0]).unwrap();
+//!
+//! assert_eq!(paper.citation_key, "key");
+//! assert_eq!(paper.year, 2018);
+//! assert_eq!(paper.author[0], Name::new("Donald", "", "Knuth", ""));
+//! assert_eq!(paper.doi, None);
+//! # }
+//! ```
+
+use model::{normalize_name, Bibliography, Bibtex};
+use names::{parse_names, Name};
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Bibliography")]
+struct BibliographyContent<'a> {
+    #[serde(borrow)]
+    entry_type: Cow<'a, str>,
+    #[serde(borrow)]
+    citation_key: Cow<'a, str>,
+    tags: Cow<'a, [(String, String)]>,
+}
+
+impl<'a> Serialize for Bibliography<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BibliographyContent {
+            entry_type: Cow::Borrowed(self.entry_type()),
+            citation_key: Cow::Borrowed(self.citation_key()),
+            tags: Cow::Borrowed(self.tags()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Bibliography<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let content = BibliographyContent::deserialize(deserializer)?;
+        Ok(Bibliography::new(
+            content.entry_type,
+            content.citation_key,
+            content.tags.into_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "Bibtex")]
+struct BibtexRef<'b, 'a: 'b> {
+    comments: &'b [Cow<'a, str>],
+    preambles: &'b [String],
+    variables: &'b [(String, String)],
+    bibliographies: &'b [Bibliography<'a>],
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Bibtex")]
+struct BibtexContent<'a> {
+    #[serde(borrow)]
+    comments: Vec<Cow<'a, str>>,
+    preambles: Vec<String>,
+    variables: Vec<(String, String)>,
+    #[serde(borrow)]
+    bibliographies: Vec<Bibliography<'a>>,
+}
+
+impl<'a> Serialize for Bibtex<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BibtexRef {
+            comments: self.comments(),
+            preambles: self.preambles(),
+            variables: self.variables(),
+            bibliographies: &self.bibliographies()[..],
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Bibtex<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let content = BibtexContent::deserialize(deserializer)?;
+        Ok(Bibtex::from_content(
+            content.comments,
+            content.preambles,
+            content.variables,
+            content.bibliographies,
+        ))
+    }
+}
+
+impl<'de, 'a> Deserializer<'de> for &'de Bibliography<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(TagsAccess {
+            bibliography: self,
+            next: 0,
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// The fields holding a list of names separated by `and`, as in the data
+/// model of *biblatex*.
+const NAME_FIELDS: &[&str] = &[
+    "afterword",
+    "annotator",
+    "author",
+    "bookauthor",
+    "commentator",
+    "editor",
+    "editora",
+    "editorb",
+    "editorc",
+    "foreword",
+    "holder",
+    "introduction",
+    "namea",
+    "nameb",
+    "namec",
+    "shortauthor",
+    "shorteditor",
+    "sortname",
+    "translator",
+];
+
+/// Give the entry type, the citation key and then the tags of a
+/// bibliography.
+struct TagsAccess<'de, 'a: 'de> {
+    bibliography: &'de Bibliography<'a>,
+    next: usize,
+    value: Option<ValueDeserializer<'de>>,
+}
+
+impl<'de, 'a> MapAccess<'de> for TagsAccess<'de, 'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let (key, value) = match self.next {
+            0 => ("entry_type".into(), self.bibliography.entry_type()),
+            1 => ("citation_key".into(), self.bibliography.citation_key()),
+            i => match self.bibliography.tags().get(i - 2) {
+                Some(tag) => (normalize_name(&tag.0), tag.1.as_ref()),
+                None => return Ok(None),
+            },
+        };
+        self.next += 1;
+        self.value = Some(ValueDeserializer {
+            value,
+            names: NAME_FIELDS.contains(&key.as_ref()),
+        });
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+        seed.deserialize(value)
+    }
+}
+
+/// Deserialize the value of a tag, which is a list of names if `names` is
+/// set.
+struct ValueDeserializer<'de> {
+    value: &'de str,
+    names: bool,
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.value.trim().parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(self.value), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.names {
+            let names = parse_names(self.value).into_iter().map(NameDeserializer);
+            return visitor.visit_seq(SeqDeserializer::new(names));
+        }
+        let values = self
+            .value
+            .split([',', ';'])
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| ValueDeserializer {
+                value,
+                names: false,
+            });
+        visitor.visit_seq(SeqDeserializer::new(values))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.value.trim().into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Deserialize a name either as a struct or as a string.
+struct NameDeserializer(Name);
+
+impl<'de> IntoDeserializer<'de, Error> for NameDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for NameDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let Name {
+            first,
+            von,
+            last,
+            jr,
+        } = self.0;
+        let parts = vec![("first", first), ("von", von), ("last", last), ("jr", jr)];
+        visitor.visit_map(MapDeserializer::new(parts.into_iter()))
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0.to_string())
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct
+        map struct enum identifier ignored_any
+    }
+}
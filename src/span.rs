@@ -0,0 +1,133 @@
+//! Locations of the parsed elements in the original *BibTeX* input.
+
+/// A position in the input.
+///
+/// `line` and `column` start at 1, the column is counted in characters.
+/// A position where every field is 0 means the element was not parsed from
+/// an input (for example a bibliography created with `Bibliography::new`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Position {
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A range of the input going from `start` (inclusive) to `end` (exclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Length of the span in bytes.
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    /// Check if the span is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Translate slices and offsets of an input into positions.
+pub(crate) struct SourceMap<'a> {
+    input: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let line_starts = ::std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceMap { input, line_starts }
+    }
+
+    /// Get the position of a byte offset.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let line_start = self.line_starts[line];
+        let column = self
+            .input
+            .get(line_start..offset)
+            .map_or(offset - line_start, |s| s.chars().count());
+        Position {
+            offset,
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// Get the span going from the `start` to the `end` byte offsets.
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        Span {
+            start: self.position(start),
+            end: self.position(end),
+        }
+    }
+
+    /// Get the span of a slice borrowed from the input.
+    pub fn span_of(&self, slice: &str) -> Span {
+        let start = slice.as_ptr() as usize - self.input.as_ptr() as usize;
+        debug_assert!(start + slice.len() <= self.input.len());
+        self.span(start, start + slice.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let map = SourceMap::new("ab\ncdé\nf");
+        assert_eq!(
+            map.position(0),
+            Position {
+                offset: 0,
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            map.position(3),
+            Position {
+                offset: 3,
+                line: 2,
+                column: 1
+            }
+        );
+        assert_eq!(
+            map.position(7),
+            Position {
+                offset: 7,
+                line: 2,
+                column: 4
+            }
+        );
+        assert_eq!(
+            map.position(8),
+            Position {
+                offset: 8,
+                line: 3,
+                column: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_span_of() {
+        let input = "@misc{key,\n title = {x}}";
+        let map = SourceMap::new(input);
+        let span = map.span_of(&input[12..17]);
+        assert_eq!(span.start.line, 2);
+        assert_eq!(span.start.column, 2);
+        assert_eq!(span.len(), 5);
+    }
+}
@@ -0,0 +1,696 @@
+//! Check the bibliographies against the data model of *BibTeX* or
+//! *biblatex*.
+//!
+//! The `Validator` reports:
+//!
+//! - the required fields missing for the entry type, such as the `journal`
+//!   of an `@article`,
+//! - the unknown entry types and fields,
+//! - the `year` which are not a number, the malformed `pages` ranges and
+//!   the `month` which are not a month,
+//! - the cross-referenced entries which are missing or cyclic.
+//!
+//! Each diagnostic has a severity, so a strict check can reject only the
+//! errors while the warnings are just displayed.
+//!
+//! ## Example
+//!
+//! ```
+//! use nom_bibtex::validate::{DiagnosticKind, Severity, Validator};
+//! use nom_bibtex::Bibtex;
+//!
+//! let bibtex = Bibtex::parse("@article{key, author = {Me}, title = {A title}, year = {2018}}").unwrap();
+//!
+//! let diagnostics = Validator::new().validate(&bibtex);
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].severity(), Severity::Error);
+//! assert_eq!(diagnostics[0].kind(), &DiagnosticKind::MissingField(vec!["journal".into()]));
+//! assert_eq!(diagnostics[0].to_string(), "error: missing field `journal` in entry `key` at 1:10");
+//! ```
+
+use crossref::InheritanceRules;
+use error::BibtexError;
+use model::{Bibliography, Bibtex};
+use span::Span;
+use std::fmt;
+
+/// The data model the bibliographies are checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataModel {
+    /// The entry types and fields of the standard *BibTeX* styles.
+    Bibtex,
+    /// The entry types and fields of *biblatex*, including the *BibTeX*
+    /// ones it aliases such as `@phdthesis` or `journal`.
+    Biblatex,
+}
+
+/// How serious a diagnostic is, ordered from the least to the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something unusual which is ignored by the styles, such as an unknown
+    /// field.
+    Info,
+    /// Something the styles can handle but probably not as expected.
+    Warning,
+    /// Something the styles can't handle, such as a missing required field.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// What a diagnostic is about.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// None of these alternative fields is defined.
+    MissingField(Vec<String>),
+    /// The entry type is not part of the data model.
+    UnknownEntryType,
+    /// The field is not part of the data model.
+    UnknownField(String),
+    /// The `year` is not a number.
+    InvalidYear,
+    /// The `pages` are not a list of pages or of ranges such as `12--34`,
+    /// or a range is decreasing.
+    InvalidPages,
+    /// The `month` is neither a month name nor a number between 1 and 12.
+    InvalidMonth,
+    /// The entry cross-referenced with this key is not defined.
+    CrossrefNotFound(String),
+    /// The entry is cross-referenced from itself.
+    CyclicCrossref,
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiagnosticKind::MissingField(ref fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| format!("`{}`", field))
+                    .collect::<Vec<_>>();
+                write!(f, "missing field {}", fields.join(" or "))
+            }
+            DiagnosticKind::UnknownEntryType => f.write_str("unknown entry type"),
+            DiagnosticKind::UnknownField(ref field) => write!(f, "unknown field `{}`", field),
+            DiagnosticKind::InvalidYear => f.write_str("`year` is not a number"),
+            DiagnosticKind::InvalidPages => f.write_str("`pages` is not a valid page range"),
+            DiagnosticKind::InvalidMonth => f.write_str("`month` is not a valid month"),
+            DiagnosticKind::CrossrefNotFound(ref key) => {
+                write!(f, "cross-referenced entry `{}` not found", key)
+            }
+            DiagnosticKind::CyclicCrossref => f.write_str("entry cross-referenced from itself"),
+        }
+    }
+}
+
+/// A problem found in a bibliography.
+///
+/// Its `Display` implementation renders a line such as
+/// `error: missing field `journal` in entry `key` at 1:10`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    kind: DiagnosticKind,
+    key: String,
+    span: Span,
+}
+
+impl Diagnostic {
+    /// Get how serious the diagnostic is.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Get what the diagnostic is about.
+    pub fn kind(&self) -> &DiagnosticKind {
+        &self.kind
+    }
+
+    /// Get the citation key of the bibliography.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the location of the field in question, or of the citation key
+    /// if the diagnostic is about the whole bibliography.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} in entry `{}`",
+            self.severity, self.kind, self.key
+        )?;
+        if self.span != Span::default() {
+            write!(f, " at {}:{}", self.span.start.line, self.span.start.column)?;
+        }
+        Ok(())
+    }
+}
+
+/// The required fields of the *BibTeX* entry types, as lists of
+/// alternatives.
+const BIBTEX_TYPES: &[(&str, &[&[&str]])] = &[
+    (
+        "article",
+        &[&["author"], &["title"], &["journal"], &["year"]],
+    ),
+    (
+        "book",
+        &[&["author", "editor"], &["title"], &["publisher"], &["year"]],
+    ),
+    ("booklet", &[&["title"]]),
+    (
+        "conference",
+        &[&["author"], &["title"], &["booktitle"], &["year"]],
+    ),
+    (
+        "inbook",
+        &[
+            &["author", "editor"],
+            &["title"],
+            &["chapter", "pages"],
+            &["publisher"],
+            &["year"],
+        ],
+    ),
+    (
+        "incollection",
+        &[
+            &["author"],
+            &["title"],
+            &["booktitle"],
+            &["publisher"],
+            &["year"],
+        ],
+    ),
+    (
+        "inproceedings",
+        &[&["author"], &["title"], &["booktitle"], &["year"]],
+    ),
+    ("manual", &[&["title"]]),
+    (
+        "mastersthesis",
+        &[&["author"], &["title"], &["school"], &["year"]],
+    ),
+    ("misc", &[]),
+    (
+        "phdthesis",
+        &[&["author"], &["title"], &["school"], &["year"]],
+    ),
+    ("proceedings", &[&["title"], &["year"]]),
+    (
+        "techreport",
+        &[&["author"], &["title"], &["institution"], &["year"]],
+    ),
+    ("unpublished", &[&["author"], &["title"], &["note"]]),
+];
+
+/// The fields of the standard *BibTeX* styles, along with the widespread
+/// fields such as `doi` or `url`.
+const BIBTEX_FIELDS: &[&str] = &[
+    "address",
+    "annote",
+    "author",
+    "booktitle",
+    "chapter",
+    "crossref",
+    "edition",
+    "editor",
+    "howpublished",
+    "institution",
+    "journal",
+    "key",
+    "month",
+    "note",
+    "number",
+    "organization",
+    "pages",
+    "publisher",
+    "school",
+    "series",
+    "title",
+    "type",
+    "volume",
+    "year",
+    // Widespread fields.
+    "abstract",
+    "archiveprefix",
+    "doi",
+    "eprint",
+    "isbn",
+    "issn",
+    "keywords",
+    "language",
+    "primaryclass",
+    "url",
+];
+
+const AUTHOR: &[&str] = &["author"];
+const AUTHOR_OR_EDITOR: &[&str] = &["author", "editor"];
+const TITLE: &[&str] = &["title"];
+const DATE: &[&str] = &["year", "date"];
+const JOURNAL: &[&str] = &["journaltitle", "journal"];
+const BOOKTITLE: &[&str] = &["booktitle"];
+const INSTITUTION: &[&str] = &["institution", "school"];
+
+/// The required fields of the *biblatex* entry types, as lists of
+/// alternatives.
+const BIBLATEX_TYPES: &[(&str, &[&[&str]])] = &[
+    ("article", &[AUTHOR, TITLE, JOURNAL, DATE]),
+    ("book", &[AUTHOR, TITLE, DATE]),
+    ("mvbook", &[AUTHOR, TITLE, DATE]),
+    ("inbook", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("bookinbook", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("suppbook", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("booklet", &[AUTHOR_OR_EDITOR, TITLE, DATE]),
+    ("collection", &[&["editor"], TITLE, DATE]),
+    ("mvcollection", &[&["editor"], TITLE, DATE]),
+    ("incollection", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("suppcollection", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("dataset", &[AUTHOR_OR_EDITOR, TITLE, DATE]),
+    ("manual", &[AUTHOR_OR_EDITOR, TITLE, DATE]),
+    ("misc", &[AUTHOR_OR_EDITOR, TITLE, DATE]),
+    (
+        "online",
+        &[AUTHOR_OR_EDITOR, TITLE, DATE, &["doi", "eprint", "url"]],
+    ),
+    ("patent", &[AUTHOR, TITLE, &["number"], DATE]),
+    ("periodical", &[&["editor"], TITLE, DATE]),
+    ("suppperiodical", &[AUTHOR, TITLE, JOURNAL, DATE]),
+    ("proceedings", &[TITLE, DATE]),
+    ("mvproceedings", &[TITLE, DATE]),
+    ("inproceedings", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("reference", &[&["editor"], TITLE, DATE]),
+    ("mvreference", &[&["editor"], TITLE, DATE]),
+    ("inreference", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("report", &[AUTHOR, TITLE, &["type"], INSTITUTION, DATE]),
+    ("set", &[&["entryset"]]),
+    ("software", &[AUTHOR_OR_EDITOR, TITLE, DATE]),
+    ("thesis", &[AUTHOR, TITLE, &["type"], INSTITUTION, DATE]),
+    ("unpublished", &[AUTHOR, TITLE, DATE]),
+    ("xdata", &[]),
+    ("customa", &[]),
+    ("customb", &[]),
+    ("customc", &[]),
+    ("customd", &[]),
+    ("custome", &[]),
+    ("customf", &[]),
+    // The aliases of the BibTeX entry types.
+    ("conference", &[AUTHOR, TITLE, BOOKTITLE, DATE]),
+    ("electronic", &[AUTHOR_OR_EDITOR, TITLE, DATE]),
+    ("mastersthesis", &[AUTHOR, TITLE, INSTITUTION, DATE]),
+    ("phdthesis", &[AUTHOR, TITLE, INSTITUTION, DATE]),
+    ("techreport", &[AUTHOR, TITLE, INSTITUTION, DATE]),
+    ("www", &[AUTHOR_OR_EDITOR, TITLE, DATE]),
+];
+
+/// The fields of *biblatex*, including the aliases of the *BibTeX* fields.
+const BIBLATEX_FIELDS: &[&str] = &[
+    "abstract",
+    "addendum",
+    "afterword",
+    "annotation",
+    "annotator",
+    "author",
+    "authortype",
+    "bookauthor",
+    "bookpagination",
+    "booksubtitle",
+    "booktitle",
+    "booktitleaddon",
+    "chapter",
+    "commentator",
+    "date",
+    "doi",
+    "edition",
+    "editor",
+    "editora",
+    "editorb",
+    "editorc",
+    "editortype",
+    "editoratype",
+    "editorbtype",
+    "editorctype",
+    "eid",
+    "entrysubtype",
+    "eprint",
+    "eprintclass",
+    "eprinttype",
+    "eventdate",
+    "eventtitle",
+    "eventtitleaddon",
+    "file",
+    "foreword",
+    "holder",
+    "howpublished",
+    "indextitle",
+    "institution",
+    "introduction",
+    "isan",
+    "isbn",
+    "ismn",
+    "isrn",
+    "issn",
+    "issue",
+    "issuesubtitle",
+    "issuetitle",
+    "iswc",
+    "journalsubtitle",
+    "journaltitle",
+    "journaltitleaddon",
+    "label",
+    "language",
+    "library",
+    "location",
+    "mainsubtitle",
+    "maintitle",
+    "maintitleaddon",
+    "month",
+    "nameaddon",
+    "note",
+    "number",
+    "organization",
+    "origdate",
+    "origlanguage",
+    "origlocation",
+    "origpublisher",
+    "origtitle",
+    "pages",
+    "pagetotal",
+    "pagination",
+    "part",
+    "publisher",
+    "pubstate",
+    "reprinttitle",
+    "series",
+    "shortauthor",
+    "shorteditor",
+    "shorthand",
+    "shorthandintro",
+    "shortjournal",
+    "shortseries",
+    "shorttitle",
+    "subtitle",
+    "title",
+    "titleaddon",
+    "translator",
+    "type",
+    "url",
+    "urldate",
+    "venue",
+    "version",
+    "volume",
+    "volumes",
+    "year",
+    // Special fields.
+    "crossref",
+    "entryset",
+    "execute",
+    "gender",
+    "langid",
+    "langidopts",
+    "ids",
+    "indexsorttitle",
+    "keywords",
+    "options",
+    "presort",
+    "related",
+    "relatedoptions",
+    "relatedtype",
+    "relatedstring",
+    "sortkey",
+    "sortname",
+    "sortshorthand",
+    "sorttitle",
+    "sortyear",
+    "xdata",
+    "xref",
+    // The aliases of the BibTeX fields.
+    "address",
+    "annote",
+    "archiveprefix",
+    "journal",
+    "key",
+    "pdf",
+    "primaryclass",
+    "school",
+];
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// A configurable checker of the bibliographies.
+///
+/// By default, the bibliographies are checked against the *BibTeX* data
+/// model, including the unknown fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validator {
+    data_model: DataModel,
+    unknown_fields: bool,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Validator {
+            data_model: DataModel::Bibtex,
+            unknown_fields: true,
+        }
+    }
+}
+
+impl Validator {
+    /// Create a validator with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the data model the bibliographies are checked against.
+    pub fn data_model(mut self, data_model: DataModel) -> Self {
+        self.data_model = data_model;
+        self
+    }
+
+    /// Report the fields which are not part of the data model.
+    pub fn unknown_fields(mut self, unknown_fields: bool) -> Self {
+        self.unknown_fields = unknown_fields;
+        self
+    }
+
+    /// Check all the bibliographies of a bibtex file content.
+    ///
+    /// The bibliographies are checked once their cross-references are
+    /// resolved following the data model, so the inherited fields count.
+    /// The missing and cyclic cross-references are reported as errors,
+    /// before the diagnostics of the bibliographies.
+    pub fn validate(&self, bibtex: &Bibtex) -> Vec<Diagnostic> {
+        let rules = match self.data_model {
+            DataModel::Bibtex => InheritanceRules::Bibtex,
+            DataModel::Biblatex => InheritanceRules::Biblatex,
+        };
+        let (bibliographies, errors) = bibtex.resolve_crossrefs(rules);
+        let mut diagnostics = errors
+            .into_iter()
+            .filter_map(|error| {
+                let (key, kind) = match error {
+                    BibtexError::CrossrefNotFound(key, crossref) => {
+                        (key, DiagnosticKind::CrossrefNotFound(crossref))
+                    }
+                    BibtexError::CyclicCrossref(key) => (key, DiagnosticKind::CyclicCrossref),
+                    _ => return None,
+                };
+                let span = bibtex
+                    .get(&key)
+                    .map(Bibliography::citation_key_span)
+                    .unwrap_or_default();
+                Some(Diagnostic {
+                    severity: Severity::Error,
+                    kind,
+                    key,
+                    span,
+                })
+            })
+            .collect::<Vec<_>>();
+        diagnostics.extend(
+            bibliographies
+                .iter()
+                .flat_map(|bibliography| self.validate_bibliography(bibliography)),
+        );
+        diagnostics
+    }
+
+    /// Check a single bibliography as is.
+    pub fn validate_bibliography(&self, bibliography: &Bibliography) -> Vec<Diagnostic> {
+        let (types, fields) = match self.data_model {
+            DataModel::Bibtex => (BIBTEX_TYPES, BIBTEX_FIELDS),
+            DataModel::Biblatex => (BIBLATEX_TYPES, BIBLATEX_FIELDS),
+        };
+        let mut diagnostics = vec![];
+        let mut report = |severity, kind, span| {
+            diagnostics.push(Diagnostic {
+                severity,
+                kind,
+                key: bibliography.citation_key().into(),
+                span,
+            })
+        };
+
+        let entry_type = bibliography.entry_type().to_lowercase();
+        match types.iter().find(|t| t.0 == entry_type) {
+            Some(&(_, required)) => {
+                for alternatives in required {
+                    if alternatives.iter().all(|f| bibliography.get(f).is_none()) {
+                        let alternatives = alternatives.iter().map(|&f| f.into()).collect();
+                        report(
+                            Severity::Error,
+                            DiagnosticKind::MissingField(alternatives),
+                            bibliography.citation_key_span(),
+                        );
+                    }
+                }
+            }
+            None => report(
+                Severity::Warning,
+                DiagnosticKind::UnknownEntryType,
+                bibliography.span(),
+            ),
+        }
+
+        let spans = bibliography.tag_spans();
+        for (i, (name, value)) in bibliography.tags().iter().enumerate() {
+            let (name_span, value_span) = spans[i];
+            let name = name.to_lowercase();
+            let kind = match name.as_ref() {
+                "year" if !is_year(value) => DiagnosticKind::InvalidYear,
+                "pages" if !is_pages(value) => DiagnosticKind::InvalidPages,
+                "month" if !is_month(value) => DiagnosticKind::InvalidMonth,
+                _ => {
+                    if self.unknown_fields && !fields.contains(&name.as_ref()) {
+                        report(
+                            Severity::Info,
+                            DiagnosticKind::UnknownField(name),
+                            name_span,
+                        );
+                    }
+                    continue;
+                }
+            };
+            report(Severity::Warning, kind, value_span);
+        }
+        diagnostics
+    }
+}
+
+fn is_year(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Check a list of pages or ranges such as `12--34, 56`.
+fn is_pages(value: &str) -> bool {
+    value.split(',').all(|range| {
+        let range = range.trim();
+        let bounds = range
+            .split(['-', '–', '—'])
+            .filter(|bound| !bound.is_empty())
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        let is_page = |page: &&str| {
+            !page.is_empty()
+                && page
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '.' || c == ':' || c == '+')
+        };
+        match bounds.len() {
+            1 => is_page(&bounds[0]) && bounds[0] == range,
+            2 => {
+                bounds.iter().all(is_page)
+                    && match (bounds[0].parse::<u64>(), bounds[1].parse::<u64>()) {
+                        (Ok(start), Ok(end)) => start <= end,
+                        _ => true,
+                    }
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Check a month name, a three letters abbreviation or a number.
+fn is_month(value: &str) -> bool {
+    let value = value.trim().trim_end_matches('.').to_lowercase();
+    match value.parse::<u32>() {
+        Ok(month) => (1..=12).contains(&month),
+        Err(_) => MONTHS
+            .iter()
+            .any(|&month| month == value || (value.len() == 3 && month.starts_with(&*value))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        assert!(is_year("2018"));
+        assert!(is_year(" 1905 "));
+        assert!(!is_year("to appear"));
+        assert!(!is_year(""));
+
+        for pages in &[
+            "12", "12--34", "12-34", "12–34", "xii--xiv", "S12--S20", "1, 3--5", "12+",
+        ] {
+            assert!(is_pages(pages), "{}", pages);
+        }
+        for pages in &["34--12", "12--", "--12", "1--2--3", "", "12 to 34"] {
+            assert!(!is_pages(pages), "{}", pages);
+        }
+
+        for month in &["January", "jan", "Sep.", "12", "3"] {
+            assert!(is_month(month), "{}", month);
+        }
+        for month in &["13", "0", "Janvier", "ja", "spring"] {
+            assert!(!is_month(month), "{}", month);
+        }
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            kind: DiagnosticKind::MissingField(vec!["author".into(), "editor".into()]),
+            key: "key".into(),
+            span: Span::default(),
+        };
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: missing field `author` or `editor` in entry `key`"
+        );
+        assert!(Severity::Error > Severity::Warning);
+        assert!(Severity::Warning > Severity::Info);
+    }
+}
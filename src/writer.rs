@@ -0,0 +1,467 @@
+//! Serialize a `Bibtex` or a `Bibliography` back to a *BibTeX* file content.
+//!
+//! Unless the values are encoded, the output is always parsed back to the
+//! same content, so `Bibtex::parse(&writer.write(&bibtex)?)` is equal to
+//! `bibtex`. The values and comments with unbalanced braces, the invalid
+//! citation keys and the names which can't be parsed back can't be written in
+//! *BibTeX* and are rejected with a `WriteError`.
+//!
+//! ## Example
+//!
+//! ```
+//! use nom_bibtex::writer::{Delimiter, Writer};
+//! use nom_bibtex::Bibtex;
+//!
+//! let bibtex = Bibtex::parse("@misc{key, title = {A title}, author = \"Me\"}").unwrap();
+//!
+//! let writer = Writer::new()
+//!     .indent("  ")
+//!     .delimiter(Delimiter::Quotes)
+//!     .trailing_comma(false);
+//! assert_eq!(
+//!     writer.write(&bibtex).unwrap(),
+//!     "@misc{key,\n  title  = \"A title\",\n  author = \"Me\"\n}\n"
+//! );
+//! ```
+
+use latex::{encode, Encoding};
+use model::{Bibliography, Bibtex};
+use parser;
+use std::fmt::{self, Write};
+
+quick_error! {
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum WriteError {
+        UnbalancedBraces (value: String) {
+            description("Value with unbalanced braces.")
+            display("Value with unbalanced braces.: {}", value)
+        }
+        UnbalancedComment (comment: String) {
+            description("Comment with unbalanced braces.")
+            display("Comment with unbalanced braces.: {}", comment)
+        }
+        InvalidCitationKey (key: String) {
+            description("Invalid citation key.")
+            display("Invalid citation key.: `{}`", key)
+        }
+        InvalidEntryType (entry_type: String) {
+            description("Invalid entry type.")
+            display("Invalid entry type.: `{}`", entry_type)
+        }
+        InvalidTagName (name: String) {
+            description("Invalid tag name.")
+            display("Invalid tag name.: `{}`", name)
+        }
+        InvalidVariableName (name: String) {
+            description("Invalid string variable name.")
+            display("Invalid string variable name.: `{}`", name)
+        }
+        Format (err: fmt::Error) {
+            description("Formatting error.")
+            display("Formatting error.: {}", err)
+            from()
+        }
+    }
+}
+
+/// The delimiter used around the values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    /// `{value}`
+    Braces,
+    /// `"value"`
+    Quotes,
+}
+
+/// The order in which the tags of a bibliography are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldOrder {
+    /// Keep the order of the tags.
+    Original,
+    /// Sort the tags by name, ignoring the case.
+    Alphabetical,
+    /// Write the given tags first, in this order and ignoring the case,
+    /// then the other tags in their original order.
+    Custom(Vec<String>),
+}
+
+/// A configurable *BibTeX* writer.
+///
+/// By default, the tags are indented with 4 spaces, aligned, delimited with
+/// braces, kept in their original order, written as is and followed by a
+/// trailing comma.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Writer {
+    indent: String,
+    align_fields: bool,
+    delimiter: Delimiter,
+    trailing_comma: bool,
+    field_order: FieldOrder,
+    encoding: Option<Encoding>,
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Writer {
+            indent: "    ".into(),
+            align_fields: true,
+            delimiter: Delimiter::Braces,
+            trailing_comma: true,
+            field_order: FieldOrder::Original,
+            encoding: None,
+        }
+    }
+}
+
+impl Writer {
+    /// Create a writer with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the indentation of the tags.
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Align the `=` of the tags of a bibliography.
+    pub fn align_fields(mut self, align_fields: bool) -> Self {
+        self.align_fields = align_fields;
+        self
+    }
+
+    /// Set the preferred delimiter of the values.
+    ///
+    /// The other delimiter is used for the values which can't be read back
+    /// with the preferred one. For example, a value starting with a space is
+    /// always quoted because bracketed values are trimmed. A value which fits
+    /// in neither, with spaces around a `"`, is split into a quoted and a
+    /// bracketed part concatenated with `#`.
+    pub fn delimiter(mut self, delimiter: Delimiter) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Add a comma after the last tag of a bibliography.
+    pub fn trailing_comma(mut self, trailing_comma: bool) -> Self {
+        self.trailing_comma = trailing_comma;
+        self
+    }
+
+    /// Set the order of the tags of a bibliography.
+    pub fn field_order(mut self, field_order: FieldOrder) -> Self {
+        self.field_order = field_order;
+        self
+    }
+
+    /// Escape the values of the tags with `encode`, to write the plain text
+    /// values of bibliographies created from another source.
+    ///
+    /// The values are written as is by default. Once escaped, a value isn't
+    /// parsed back to the same content, unless it is decoded.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Write a whole bibtex file content.
+    ///
+    /// The comments come first, then the preambles, the string variables
+    /// and the bibliographies.
+    pub fn write(&self, bibtex: &Bibtex) -> Result<String, WriteError> {
+        let mut out = String::new();
+        self.write_to(bibtex, &mut out)?;
+        Ok(out)
+    }
+
+    /// Write a single bibliography.
+    pub fn write_bibliography(&self, bibliography: &Bibliography) -> Result<String, WriteError> {
+        let mut out = String::new();
+        self.write_bibliography_to(bibliography, &mut out)?;
+        Ok(out)
+    }
+
+    /// Write a whole bibtex file content into `out`.
+    pub fn write_to<W: Write>(&self, bibtex: &Bibtex, out: &mut W) -> Result<(), WriteError> {
+        let mut first = true;
+        let mut separate = |out: &mut W| {
+            if first {
+                first = false;
+                Ok(())
+            } else {
+                out.write_char('\n')
+            }
+        };
+
+        for comment in bibtex.comments() {
+            // Written outside of an entry, an unbalanced comment would be
+            // merged with the next one once parsed back.
+            if !is_balanced(comment) {
+                return Err(WriteError::UnbalancedComment(comment.to_string()));
+            }
+            separate(out)?;
+            writeln!(out, "@comment{{{}}}", comment)?;
+        }
+        for preamble in bibtex.preambles() {
+            separate(out)?;
+            writeln!(out, "@preamble{{{}}}", self.delimit(preamble)?)?;
+        }
+        for (key, value) in bibtex.variables() {
+            if !parser::is_identifier(key) {
+                return Err(WriteError::InvalidVariableName(key.clone()));
+            }
+            separate(out)?;
+            writeln!(out, "@string{{{} = {}}}", key, self.delimit(value)?)?;
+        }
+        for bibliography in bibtex.bibliographies() {
+            separate(out)?;
+            self.write_bibliography_to(bibliography, out)?;
+        }
+        Ok(())
+    }
+
+    /// Write a single bibliography into `out`.
+    pub fn write_bibliography_to<W: Write>(
+        &self,
+        bibliography: &Bibliography,
+        out: &mut W,
+    ) -> Result<(), WriteError> {
+        let entry_type = bibliography.entry_type();
+        if !parser::is_bibliography_type(entry_type) {
+            return Err(WriteError::InvalidEntryType(entry_type.into()));
+        }
+        let delimiters = bibliography.delimiters();
+        let citation_key = bibliography.citation_key();
+        if !parser::is_citation_key(citation_key, delimiters) {
+            return Err(WriteError::InvalidCitationKey(citation_key.into()));
+        }
+        if let Some(tag) = bibliography
+            .tags()
+            .iter()
+            .find(|tag| !parser::is_identifier(&tag.0))
+        {
+            return Err(WriteError::InvalidTagName(tag.0.clone()));
+        }
+
+        let tags = self.ordered_tags(bibliography.tags());
+        let width = if self.align_fields {
+            tags.iter()
+                .map(|tag| tag.0.chars().count())
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        writeln!(out, "@{}{}{},", entry_type, delimiters.open(), citation_key)?;
+        for (i, &(key, value)) in tags.iter().enumerate() {
+            write!(
+                out,
+                "{}{:width$} = {}",
+                self.indent,
+                key,
+                self.tag_value(value)?,
+                width = width
+            )?;
+            if i + 1 < tags.len() || self.trailing_comma {
+                out.write_char(',')?;
+            }
+            out.write_char('\n')?;
+        }
+        writeln!(out, "{}", delimiters.close())?;
+        Ok(())
+    }
+
+    fn ordered_tags<'t>(&self, tags: &'t [(String, String)]) -> Vec<&'t (String, String)> {
+        let mut tags = tags.iter().collect::<Vec<_>>();
+        match self.field_order {
+            FieldOrder::Original => {}
+            FieldOrder::Alphabetical => tags.sort_by_key(|tag| tag.0.to_lowercase()),
+            FieldOrder::Custom(ref order) => tags.sort_by_key(|tag| {
+                order
+                    .iter()
+                    .position(|key| key.eq_ignore_ascii_case(&tag.0))
+                    .unwrap_or(order.len())
+            }),
+        }
+        tags
+    }
+
+    /// Delimit the value of a tag.
+    fn tag_value(&self, value: &str) -> Result<String, WriteError> {
+        let encoded;
+        let value = match self.encoding {
+            Some(encoding) => {
+                encoded = encode(value, encoding);
+                &encoded
+            }
+            None => value,
+        };
+        self.delimit(value)
+    }
+
+    /// Delimit a value with the preferred delimiter if possible.
+    fn delimit(&self, value: &str) -> Result<String, WriteError> {
+        if !is_balanced(value) {
+            return Err(WriteError::UnbalancedBraces(value.into()));
+        }
+        let braces = can_use_braces(value);
+        let quotes = !has_top_level_quote(value);
+        Ok(match self.delimiter {
+            Delimiter::Quotes if quotes => format!("\"{}\"", value),
+            _ if braces => format!("{{{}}}", value),
+            _ if quotes => format!("\"{}\"", value),
+            _ => {
+                // Only the spaces around the value need to be quoted.
+                let trimmed = value.trim_start();
+                let start = &value[..value.len() - trimmed.len()];
+                let end = &trimmed[trimmed.trim_end().len()..];
+                let mut parts = vec![];
+                if !start.is_empty() {
+                    parts.push(format!("\"{}\"", start));
+                }
+                parts.push(format!("{{{}}}", trimmed.trim_end()));
+                if !end.is_empty() {
+                    parts.push(format!("\"{}\"", end));
+                }
+                parts.join(" # ")
+            }
+        })
+    }
+}
+
+/// Check that every brace of the value is matched.
+fn is_balanced(value: &str) -> bool {
+    let mut depth = 0;
+    for c in value.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return false,
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+/// Check if the value has a `"` which is not enclosed in braces.
+fn has_top_level_quote(value: &str) -> bool {
+    let mut depth = 0;
+    for c in value.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '"' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Bracketed values are trimmed.
+fn can_use_braces(value: &str) -> bool {
+    value.trim() == value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bibliography() -> Bibliography<'static> {
+        Bibliography::new(
+            "article",
+            "key",
+            vec![
+                ("title".into(), "A {Title}".into()),
+                ("Year".into(), "2018".into()),
+                ("author".into(), "Me".into()),
+                ("note".into(), "me@example.com".into()),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_write_bibliography() {
+        assert_eq!(
+            Writer::new().write_bibliography(&bibliography()).unwrap(),
+            "@article{key,
+    title  = {A {Title}},
+    Year   = {2018},
+    author = {Me},
+    note   = {me@example.com},
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_write_bibliography_configured() {
+        let writer = Writer::new()
+            .indent("\t")
+            .align_fields(false)
+            .delimiter(Delimiter::Quotes)
+            .trailing_comma(false)
+            .field_order(FieldOrder::Custom(vec!["author".into(), "year".into()]));
+        assert_eq!(
+            writer.write_bibliography(&bibliography()).unwrap(),
+            "@article{key,
+\tauthor = \"Me\",
+\tYear = \"2018\",
+\ttitle = \"A {Title}\",
+\tnote = \"me@example.com\"
+}
+"
+        );
+
+        let writer = Writer::new().field_order(FieldOrder::Alphabetical);
+        let written = writer.write_bibliography(&bibliography()).unwrap();
+        let keys = written
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_whitespace().next())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["author", "note", "title", "Year", "}"]);
+    }
+
+    #[test]
+    fn test_tag_value() {
+        let writer = Writer::new();
+        assert_eq!(writer.tag_value("{A} title").unwrap(), "{{A} title}");
+        assert_eq!(writer.tag_value(" spaced ").unwrap(), "\" spaced \"");
+        assert_eq!(writer.tag_value("a@b").unwrap(), "{a@b}");
+
+        assert_eq!(
+            writer.tag_value("a \"quoted\" word").unwrap(),
+            "{a \"quoted\" word}"
+        );
+
+        let writer = Writer::new().delimiter(Delimiter::Quotes);
+        assert_eq!(writer.tag_value("{A} title").unwrap(), "\"{A} title\"");
+        assert_eq!(
+            writer.tag_value("a \"quoted\" word").unwrap(),
+            "{a \"quoted\" word}"
+        );
+        assert_eq!(
+            writer.tag_value("a {\"} quote").unwrap(),
+            "\"a {\"} quote\""
+        );
+
+        assert_eq!(
+            writer.tag_value(" a \"quoted\" word ").unwrap(),
+            "\" \" # {a \"quoted\" word} # \" \""
+        );
+        assert_eq!(
+            writer.tag_value("\"quoted\"\t").unwrap(),
+            "{\"quoted\"} # \"\t\""
+        );
+        assert_eq!(
+            writer.tag_value("a } b"),
+            Err(WriteError::UnbalancedBraces("a } b".into()))
+        );
+        assert_eq!(
+            writer.tag_value("{a"),
+            Err(WriteError::UnbalancedBraces("{a".into()))
+        );
+    }
+}
@@ -1,6 +1,12 @@
 extern crate nom_bibtex;
 
-use nom_bibtex::Bibtex;
+use nom_bibtex::crossref::InheritanceRules;
+use nom_bibtex::error::{BibtexError, DuplicateKind, Expected, ParseError};
+use nom_bibtex::latex::decode;
+use nom_bibtex::model::{CommentStyle, Delimiters, DuplicatePolicy, StringValueType};
+use nom_bibtex::names::Name;
+use nom_bibtex::validate::{DataModel, DiagnosticKind, Severity, Validator};
+use nom_bibtex::{Bibtex, ParseOptions};
 use std::fs::File;
 use std::io::prelude::*;
 
@@ -40,3 +46,987 @@ fn test_bib() {
     assert_eq!(b2.citation_key(), "knuthwebsite");
     assert_eq!(b2.tags()[0], ("author".into(), "Donald Knuth".into()));
 }
+
+#[test]
+fn test_bib_names() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+
+    let b0 = &bibtex.bibliographies()[0];
+    assert_eq!(b0.authors(), vec![Name::new("Albert", "", "Einstein", "")]);
+    assert!(b0.editors().is_empty());
+
+    let b1 = &bibtex.bibliographies()[1];
+    let last_names = b1
+        .authors()
+        .into_iter()
+        .map(|name| name.last)
+        .collect::<Vec<_>>();
+    assert_eq!(last_names, vec!["Goossens", "Mittelbach", "Samarin"]);
+
+    let bibtex = Bibtex::parse(
+        "@book{ key, Editor = {van Beethoven, Ludwig and {Barnes and Noble} and others} }",
+    )
+    .unwrap();
+    let editors = bibtex.bibliographies()[0].editors();
+    assert_eq!(
+        editors[..2],
+        [
+            Name::new("Ludwig", "van", "Beethoven", ""),
+            Name::new("", "", "{Barnes and Noble}", ""),
+        ]
+    );
+    assert!(editors[2].is_others());
+}
+
+#[test]
+fn test_bib_lookup() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+
+    let b0 = &bibtex.bibliographies()[0];
+    assert_eq!(
+        b0.get("doi"),
+        Some("http://dx.doi.org/10.1002/andp.19053221004")
+    );
+    assert_eq!(b0.get("DOI"), b0.get("Doi"));
+    assert_eq!(b0.get("missing"), None);
+    assert!(b0.title().unwrap().starts_with("{Zur Elektrodynamik"));
+    assert_eq!(b0.year(), Some(1905));
+    // The original spelling and order are kept.
+    assert_eq!(b0.tags().last().unwrap().0, "DOI");
+
+    let bibtex = Bibtex::parse(
+        "@misc{ a, date = {2018-03-21}, Title = {First}, title = {Second} }
+         @misc{ b, year = {to appear} }
+         @misc{ c, year = {2018a}, date = {2018-03} }",
+    )
+    .unwrap();
+    let bibliographies = bibtex.bibliographies();
+    assert_eq!(bibliographies[0].year(), Some(2018));
+    assert_eq!(bibliographies[0].title(), Some("First"));
+    assert_eq!(bibliographies[1].year(), None);
+    assert_eq!(bibliographies[1].title(), None);
+    assert_eq!(bibliographies[2].year(), Some(2018));
+}
+
+#[test]
+fn test_bib_get() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+    assert_eq!(
+        bibtex.get("latexcompanion"),
+        Some(&bibtex.bibliographies()[1])
+    );
+    assert_eq!(
+        bibtex.get("KnuthWebsite").map(|b| b.citation_key()),
+        Some("knuthwebsite")
+    );
+    assert_eq!(bibtex.get("missing"), None);
+
+    let bib_str = "@misc{ Knuth, title = {First} } @misc{ knuth, title = {Second} }";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    assert_eq!(bibtex.get("KNUTH").unwrap().title(), Some("First"));
+
+    let options = ParseOptions::new().case_sensitive_keys(true);
+    let bibtex = Bibtex::parse_with(bib_str, &options).unwrap();
+    assert_eq!(bibtex.get("knuth").unwrap().title(), Some("Second"));
+    assert_eq!(bibtex.get("KNUTH"), None);
+}
+
+#[test]
+fn test_bib_duplicates() {
+    let bib_str = "@string{ pub = \"First\" }
+@string{ PUB = \"Second\" }
+@misc{ key,
+    publisher = pub,
+    title = {A},
+    Title = {B},
+}
+@misc{ Key, title = {Other} }";
+
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let duplicates = bibtex.duplicates();
+    assert_eq!(
+        duplicates
+            .iter()
+            .map(|d| (d.kind(), d.name(), d.key()))
+            .collect::<Vec<_>>(),
+        vec![
+            (DuplicateKind::StringVariable, "PUB", None),
+            (DuplicateKind::Tag, "Title", Some("key")),
+            (DuplicateKind::CitationKey, "Key", None),
+        ]
+    );
+    assert_eq!(
+        (
+            duplicates[1].first().start.line,
+            duplicates[1].second().start.line
+        ),
+        (5, 6)
+    );
+    assert_eq!(
+        duplicates[1].to_string(),
+        "tag `Title` in entry `key` at 6:5, first defined at 5:5"
+    );
+    assert_eq!(bibtex.variables(), &vec![("pub".into(), "First".into())]);
+    assert_eq!(bibtex.bibliographies().len(), 1);
+    assert_eq!(
+        bibtex.bibliographies()[0].tags(),
+        &vec![
+            ("publisher".into(), "First".into()),
+            ("title".into(), "A".into())
+        ]
+    );
+
+    let options = ParseOptions::new().duplicates(DuplicatePolicy::LastWins);
+    let bibtex = Bibtex::parse_with(bib_str, &options).unwrap();
+    assert_eq!(bibtex.duplicates().len(), 3);
+    assert_eq!(bibtex.variables(), &vec![("PUB".into(), "Second".into())]);
+    assert_eq!(bibtex.bibliographies().len(), 1);
+    assert_eq!(bibtex.bibliographies()[0].title(), Some("Other"));
+    assert_eq!(bibtex.get("key").unwrap().citation_key(), "Key");
+
+    let options = ParseOptions::new().duplicates(DuplicatePolicy::Error);
+    match Bibtex::parse_with(bib_str, &options) {
+        Err(BibtexError::Duplicate(duplicate)) => {
+            assert_eq!(duplicate.kind(), DuplicateKind::StringVariable)
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    let (bibtex, errors) = Bibtex::parse_lenient_with(bib_str, &options);
+    assert_eq!(errors.len(), 3);
+    assert_eq!(bibtex.variables(), &vec![("pub".into(), "First".into())]);
+
+    let options = ParseOptions::new().case_sensitive_keys(true);
+    let bibtex = Bibtex::parse_with(bib_str, &options).unwrap();
+    assert_eq!(bibtex.bibliographies().len(), 2);
+
+    // The keys, tags and variables are compared alike, only the case of the
+    // ASCII letters is ignored.
+    let bib_str = "@string{ Über = \"A\" }
+@string{ über = \"B\" }
+@string{ ÜBER = \"C\" }
+@misc{ Ökey, Ärger = Über, ärger = über, ÄRGER = {C} }
+@misc{ ökey, title = {B} }
+@misc{ ÖKEY, title = {C} }";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    assert_eq!(
+        bibtex
+            .duplicates()
+            .iter()
+            .map(|d| (d.kind(), d.name(), d.key()))
+            .collect::<Vec<_>>(),
+        vec![
+            (DuplicateKind::StringVariable, "ÜBER", None),
+            (DuplicateKind::Tag, "ÄRGER", Some("Ökey")),
+            (DuplicateKind::CitationKey, "ÖKEY", None),
+        ]
+    );
+    assert_eq!(bibtex.variables().len(), 2);
+    let bibliography = bibtex.get("ÖKEY").unwrap();
+    assert_eq!(bibliography.citation_key(), "Ökey");
+    assert_eq!(bibliography.get("ÄRGER"), Some("A"));
+    assert_eq!(bibliography.get("ärger"), Some("B"));
+    assert_eq!(bibtex.get("ökey").unwrap().title(), Some("B"));
+}
+
+#[test]
+fn test_bib_validation() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+    assert_eq!(Validator::new().validate(&bibtex), vec![]);
+
+    let diagnostics = Validator::new()
+        .data_model(DataModel::Biblatex)
+        .validate(&bibtex);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.key(), d.kind().clone()))
+            .collect::<Vec<_>>(),
+        vec![(
+            "knuthwebsite",
+            DiagnosticKind::MissingField(vec!["year".into(), "date".into()])
+        )]
+    );
+
+    let bib_str = "@inproceedings{ child, author = {Me}, title = {Child}, crossref = {parent} }
+@proceedings{ parent, title = {Parent}, year = {2020}, month = {Janvier}, pages = {34--12} }
+@article{ article, title = {Article}, year = {soon}, note = {A}, color = {blue} }
+@recipe{ recipe, title = {Recipe} }";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let diagnostics = Validator::new().validate(&bibtex);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.severity(), d.key(), d.kind().clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                Severity::Error,
+                "child",
+                DiagnosticKind::MissingField(vec!["booktitle".into()])
+            ),
+            (Severity::Warning, "child", DiagnosticKind::InvalidMonth),
+            (Severity::Warning, "child", DiagnosticKind::InvalidPages),
+            (Severity::Warning, "parent", DiagnosticKind::InvalidMonth),
+            (Severity::Warning, "parent", DiagnosticKind::InvalidPages),
+            (
+                Severity::Error,
+                "article",
+                DiagnosticKind::MissingField(vec!["author".into()])
+            ),
+            (
+                Severity::Error,
+                "article",
+                DiagnosticKind::MissingField(vec!["journal".into()])
+            ),
+            (Severity::Warning, "article", DiagnosticKind::InvalidYear),
+            (
+                Severity::Info,
+                "article",
+                DiagnosticKind::UnknownField("color".into())
+            ),
+            (
+                Severity::Warning,
+                "recipe",
+                DiagnosticKind::UnknownEntryType
+            ),
+        ]
+    );
+    assert_eq!(
+        diagnostics[7].to_string(),
+        "warning: `year` is not a number in entry `article` at 3:46"
+    );
+
+    // With biblatex, the title of the parent becomes the booktitle.
+    let diagnostics = Validator::new()
+        .data_model(DataModel::Biblatex)
+        .unknown_fields(false)
+        .validate(&bibtex);
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.kind() != &DiagnosticKind::MissingField(vec!["booktitle".into()])));
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.kind() != &DiagnosticKind::UnknownField("color".into())));
+}
+
+#[test]
+fn test_bib_validation_crossrefs() {
+    let bib_str = "@misc{ orphan, title = {Orphan}, crossref = {missing} }
+@misc{ loop1, title = {Loop}, crossref = {loop2} }
+@misc{ loop2, title = {Loop}, crossref = {loop1} }";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let diagnostics = Validator::new().validate(&bibtex);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.severity(), d.key(), d.kind().clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                Severity::Error,
+                "orphan",
+                DiagnosticKind::CrossrefNotFound("missing".into())
+            ),
+            (Severity::Error, "loop2", DiagnosticKind::CyclicCrossref),
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "error: cross-referenced entry `missing` not found in entry `orphan` at 1:8"
+    );
+}
+
+#[test]
+fn test_bib_decoded_values() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+    let bibliographies = bibtex.bibliographies();
+
+    let title = decode(&bibliographies[0].tags()[1].1);
+    assert!(title
+        .text
+        .starts_with("Zur Elektrodynamik bewegter Körper. (German) ["));
+    assert!(title.unknown_commands.is_empty());
+
+    assert_eq!(
+        decode(&bibliographies[1].tags()[1].1).text,
+        "The LaTeX Companion"
+    );
+    assert_eq!(
+        decode(&bibliographies[2].tags()[2].1).text,
+        "http://www-cs-faculty.stanford.edu/~uno/abcde.html"
+    );
+}
+
+#[test]
+fn test_bib_raw_values() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+
+    let b0 = &bibtex.bibliographies()[0];
+    assert_eq!(b0.raw_values().len(), b0.tags().len());
+    assert_eq!(
+        b0.raw_values()[0],
+        vec![StringValueType::Abbreviation("ae")]
+    );
+    assert_eq!(b0.raw_values()[4], vec![StringValueType::Number("10")]);
+
+    let b1 = &bibtex.bibliographies()[1];
+    assert_eq!(
+        b1.raw_values()[4],
+        vec![
+            StringValueType::Str("Reading, "),
+            StringValueType::Abbreviation("mass"),
+        ]
+    );
+    assert_eq!(
+        b1.tags()[4],
+        ("address".into(), "Reading, Massachusetts".into())
+    );
+}
+
+#[test]
+fn test_bib_unexpanded() {
+    let bib_str = read_file("samples/test.bib");
+    let options = ParseOptions::new().expand_variables(false);
+    let bibtex = Bibtex::parse_with(&bib_str, &options).unwrap();
+
+    assert_eq!(bibtex.variables()[4], ("ae".into(), "alb ein".into()));
+
+    let b1 = &bibtex.bibliographies()[1];
+    assert_eq!(b1.tags()[4], ("address".into(), "Reading, mass".into()));
+    assert_eq!(
+        b1.raw_values()[4],
+        vec![
+            StringValueType::Str("Reading, "),
+            StringValueType::Abbreviation("mass"),
+        ]
+    );
+
+    // Undefined variables are not an error without the expansion.
+    let bib_str = "@misc{ key, journal = jcp }";
+    assert!(Bibtex::parse(bib_str).is_err());
+    let bibtex = Bibtex::parse_with(bib_str, &options).unwrap();
+    assert_eq!(bibtex.bibliographies()[0].tags()[0].1, "jcp");
+
+    let (bibtex, errors) = Bibtex::parse_lenient_with(bib_str, &options);
+    assert!(errors.is_empty());
+    assert_eq!(bibtex.bibliographies().len(), 1);
+}
+
+#[test]
+fn test_bib_value_grammar() {
+    let bib_str = "@string{ braced = {Braced {nested}} }
+@string{ quoted = \"Quoted\" }
+@string{ number = 2020 }
+@string{ mixed = braced # \" and \" # {braced} # 1 }
+@preamble{ {\\newcommand{\\noop}[1]{}} # \"\\noop\" # number }
+@preamble{ \\newcommand{\\noopsort}[1]{} }
+@misc{ key,
+    a = {a} # \"b\" # 3 # quoted,
+    b = 3 # {a} # quoted # \"b\",
+    c = quoted # 3 # \"b\" # {a},
+    d = \"b\" # quoted # {a} # 3,
+    e = number#number,
+    f = mixed,
+}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    assert_eq!(
+        bibtex.variables(),
+        &[
+            ("braced".into(), "Braced {nested}".into()),
+            ("quoted".into(), "Quoted".into()),
+            ("number".into(), "2020".into()),
+            ("mixed".into(), "Braced {nested} and braced1".into()),
+        ][..]
+    );
+    assert_eq!(
+        bibtex.preambles(),
+        &vec![
+            "\\newcommand{\\noop}[1]{}\\noop2020".to_string(),
+            "\\newcommand{\\noopsort}[1]{}".to_string(),
+        ]
+    );
+    let values = bibtex.bibliographies()[0]
+        .tags()
+        .iter()
+        .map(|tag| tag.1.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            "ab3Quoted",
+            "3aQuotedb",
+            "Quoted3ba",
+            "bQuoteda3",
+            "20202020",
+            "Braced {nested} and braced1",
+        ]
+    );
+
+    let err = parsing_error("@misc{key, title = {A} # }");
+    assert_eq!(err.expected(), Expected::ClosingBrace);
+    let err = parsing_error("@string{key = {A} \"B\"}");
+    assert_eq!(err.expected(), Expected::ClosingBrace);
+}
+
+#[test]
+fn test_bib_parentheses() {
+    let bib_str = "@comment( A {(nested)} comment )
+@preamble( \"A\" # { (preamble)} )
+@string( name = {Me} )
+@article( key,
+    author = name,
+    title = {Braces (and parentheses)},
+)
+@misc{ other, title = \"Braces\" }";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    assert_eq!(bibtex.comments(), &vec!["A {(nested)} comment"]);
+    assert_eq!(bibtex.preambles(), &vec!["A(preamble)".to_string()]);
+    assert_eq!(bibtex.variables(), &[("name".into(), "Me".into())][..]);
+
+    let bibliographies = bibtex.bibliographies();
+    assert_eq!(bibliographies[0].delimiters(), Delimiters::Parentheses);
+    assert_eq!(bibliographies[0].get("author"), Some("Me"));
+    assert_eq!(bibliographies[0].title(), Some("Braces (and parentheses)"));
+    assert_eq!(bibliographies[1].delimiters(), Delimiters::Braces);
+
+    let err = parsing_error("@misc( key, title = {A} }");
+    assert_eq!(
+        (err.expected(), err.column()),
+        (Expected::ClosingParenthesis, 25)
+    );
+    let err = parsing_error("@comment( An } unbalanced comment )");
+    assert_eq!(err.expected(), Expected::Comment);
+    let err = parsing_error("@preamble( \"A\" ");
+    assert_eq!(err.expected(), Expected::ClosingParenthesis);
+    assert_eq!(err.context(), &["a preamble"][..]);
+}
+
+#[test]
+fn test_bib_comments() {
+    let bib_str = "Contact me@example.com for the sources.
+@comment{ Disabled:
+@misc{ disabled, title = {Disabled} }
+}
+@misc{ key, note = {Written by me@example.com}, title = \"@ home\" }";
+
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    assert_eq!(
+        bibtex.comments(),
+        &vec![
+            "Contact me@example.com for the sources.",
+            "Disabled:\n@misc{ disabled, title = {Disabled} }",
+        ]
+    );
+    let keys = bibtex
+        .bibliographies()
+        .iter()
+        .map(|b| b.citation_key())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec!["key"]);
+    assert_eq!(
+        bibtex.bibliographies()[0].get("note"),
+        Some("Written by me@example.com")
+    );
+    assert_eq!(bibtex.bibliographies()[0].title(), Some("@ home"));
+
+    // An `@` which doesn't open an entry is a part of the comment.
+    let bibtex = Bibtex::parse("Write to @john for questions\n@misc{k, title={A}}").unwrap();
+    assert_eq!(bibtex.comments(), &vec!["Write to @john for questions"]);
+    assert_eq!(bibtex.bibliographies()[0].citation_key(), "k");
+    let bibtex = Bibtex::parse("@misc{k, title={A}}\njunk @ here").unwrap();
+    assert_eq!(bibtex.comments(), &vec!["junk @ here"]);
+    assert_eq!(bibtex.bibliographies().len(), 1);
+
+    let options = ParseOptions::new().comments(CommentStyle::Bibtex);
+    let bibtex = Bibtex::parse_with(bib_str, &options).unwrap();
+    assert_eq!(
+        bibtex.comments(),
+        &vec![
+            "Contact me@example.com for the sources.",
+            "{ Disabled:",
+            "}"
+        ]
+    );
+    let keys = bibtex
+        .bibliographies()
+        .iter()
+        .map(|b| b.citation_key())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec!["disabled", "key"]);
+
+    let (bibtex, errors) = Bibtex::parse_lenient(
+        "@misc{ broken, title {Broken}, note = {a@b.org} }
+@misc{ last, title = {Last} }",
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(bibtex.bibliographies()[0].citation_key(), "last");
+}
+
+#[test]
+fn test_bib_citation_keys() {
+    let bib_str = "@misc{doi:10.1000/182}
+@book{ Müller2001/ed.+1 , title = {Title} }
+@misc( empty )
+@article{last,}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let keys = bibtex
+        .bibliographies()
+        .iter()
+        .map(|b| b.citation_key())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        vec!["doi:10.1000/182", "Müller2001/ed.+1", "empty", "last"]
+    );
+    assert!(bibtex.bibliographies()[0].tags().is_empty());
+    assert_eq!(bibtex.bibliographies()[1].title(), Some("Title"));
+
+    let err = parsing_error("@misc{my key, title = {A}}");
+    assert_eq!(
+        (err.expected(), err.column(), err.key()),
+        (Expected::CitationKey, 10, Some("my"))
+    );
+    let err = parsing_error("@misc{a{b}, title = {A}}");
+    assert_eq!((err.expected(), err.column()), (Expected::CitationKey, 8));
+    let err = parsing_error("@misc{title = {A}}");
+    assert_eq!((err.expected(), err.column()), (Expected::CitationKey, 13));
+    let err = parsing_error("@misc{key");
+    assert_eq!(err.expected(), Expected::CitationKey);
+    let err = parsing_error("@misc{, title={A}}");
+    assert_eq!((err.expected(), err.column()), (Expected::CitationKey, 7));
+    let err = parsing_error("@misc{}");
+    assert_eq!((err.expected(), err.column()), (Expected::CitationKey, 7));
+}
+
+#[test]
+fn test_bib_predefined_macros() {
+    let bib_str = "@string{ dec = \"Last month\" }
+@string{ date = jan # \" 2020\" }
+@misc{ key, month = feb, note = date, other = dec, journal = jcp }";
+
+    let bibtex = Bibtex::parse_with(
+        bib_str,
+        &ParseOptions::new().macros(vec![("jcp", "J. Chem. Phys.")]),
+    )
+    .unwrap();
+    assert_eq!(
+        bibtex.variables()[1],
+        ("date".into(), "January 2020".into())
+    );
+    let tags = bibtex.bibliographies()[0].tags();
+    assert_eq!(tags[0].1, "February");
+    assert_eq!(tags[1].1, "January 2020");
+    // The variables of the file come first.
+    assert_eq!(tags[2].1, "Last month");
+    assert_eq!(tags[3].1, "J. Chem. Phys.");
+    // Predefined macros are not variables of the file.
+    assert_eq!(bibtex.variables().len(), 2);
+
+    let options = ParseOptions::new()
+        .macros_from_bibtex("@string{ jcp = \"J. Chem. Phys.\" }")
+        .unwrap();
+    assert_eq!(
+        options.predefined_macros().get("jcp").map(String::as_str),
+        Some("J. Chem. Phys.")
+    );
+    assert!(Bibtex::parse_with(bib_str, &options).is_ok());
+
+    assert_eq!(
+        Bibtex::parse_with(bib_str, &ParseOptions::new().clear_macros()),
+        Err(BibtexError::StringVariableNotFound("jan".into()))
+    );
+    assert_eq!(
+        Bibtex::parse(bib_str),
+        Err(BibtexError::StringVariableNotFound("jcp".into()))
+    );
+}
+
+#[test]
+fn test_bib_macro_case() {
+    let bib_str = "@string{ Foo = \"Foo\" }
+@string{ both = FOO # \" and \" # foo }
+@misc{ key, month = Jan, note = BOTH, journal = JCP, title = fOO }";
+
+    let options = ParseOptions::new().macros(vec![("Jcp", "J. Chem. Phys.")]);
+    assert_eq!(
+        options.predefined_macros().get("jcp").map(String::as_str),
+        Some("J. Chem. Phys.")
+    );
+    let bibtex = Bibtex::parse_with(bib_str, &options).unwrap();
+    assert_eq!(bibtex.variables()[1], ("both".into(), "Foo and Foo".into()));
+    let bibliography = &bibtex.bibliographies()[0];
+    assert_eq!(bibliography.get("month"), Some("January"));
+    assert_eq!(bibliography.get("note"), Some("Foo and Foo"));
+    assert_eq!(bibliography.get("journal"), Some("J. Chem. Phys."));
+    assert_eq!(bibliography.title(), Some("Foo"));
+}
+
+#[test]
+fn test_bib_crossrefs() {
+    let bib_str = "@inproceedings{ child,
+    author = {Me},
+    title = {Child},
+    crossref = {parent},
+}
+@proceedings{ parent,
+    title = {Parent},
+    Editor = {You},
+    year = 2020,
+    crossref = {series},
+    shorthand = {P},
+}
+@mvproceedings{ series, title = {Series}, publisher = {Pub} }
+@xdata{ common, location = {Here} }
+@article{ withdata, xdata = {common}, crossref = missing }
+@misc{ loop1, crossref = {loop2} }
+@misc{ loop2, crossref = {loop1} }";
+    let bibtex = Bibtex::parse_with(bib_str, &ParseOptions::new().expand_variables(false)).unwrap();
+
+    let tags = |bibliographies: &[nom_bibtex::Bibliography], key: &str| {
+        bibliographies
+            .iter()
+            .find(|bib| bib.citation_key() == key)
+            .unwrap()
+            .tags()
+            .clone()
+    };
+    let pairs = |tags: &[(&str, &str)]| {
+        tags.iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+    };
+
+    let (resolved, errors) = bibtex.resolve_crossrefs(InheritanceRules::Bibtex);
+    assert_eq!(
+        tags(&resolved, "child"),
+        pairs(&[
+            ("author", "Me"),
+            ("title", "Child"),
+            ("crossref", "parent"),
+            ("Editor", "You"),
+            ("year", "2020"),
+            ("shorthand", "P"),
+            ("publisher", "Pub"),
+        ])
+    );
+    assert_eq!(resolved.len(), bibtex.bibliographies().len());
+    assert_eq!(
+        errors,
+        vec![
+            BibtexError::CrossrefNotFound("withdata".into(), "missing".into()),
+            BibtexError::CyclicCrossref("loop2".into()),
+        ]
+    );
+
+    let (resolved, errors) = bibtex.resolve_crossrefs(InheritanceRules::Biblatex);
+    assert_eq!(
+        tags(&resolved, "child"),
+        pairs(&[
+            ("author", "Me"),
+            ("title", "Child"),
+            ("crossref", "parent"),
+            ("booktitle", "Parent"),
+            ("Editor", "You"),
+            ("year", "2020"),
+            ("maintitle", "Series"),
+            ("publisher", "Pub"),
+        ])
+    );
+    assert_eq!(resolved[0].get("editor"), Some("You"));
+    assert_eq!(
+        tags(&resolved, "withdata"),
+        pairs(&[
+            ("xdata", "common"),
+            ("crossref", "missing"),
+            ("location", "Here"),
+        ])
+    );
+    assert!(resolved.iter().all(|bib| bib.entry_type() != "xdata"));
+    assert_eq!(errors.len(), 2);
+
+    // The bibliographies are not modified.
+    assert_eq!(tags(bibtex.bibliographies(), "child").len(), 3);
+}
+
+#[test]
+fn test_bib_crossrefs_case() {
+    let bib_str = "@misc{ child, crossref = {Parent} }
+@book{ parent, title = {Parent} }";
+
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let (resolved, errors) = bibtex.resolve_crossrefs(InheritanceRules::Bibtex);
+    assert!(errors.is_empty());
+    assert_eq!(resolved[0].get("title"), Some("Parent"));
+
+    let options = ParseOptions::new().case_sensitive_keys(true);
+    let bibtex = Bibtex::parse_with(bib_str, &options).unwrap();
+    let (resolved, errors) = bibtex.resolve_crossrefs(InheritanceRules::Bibtex);
+    assert_eq!(
+        errors,
+        vec![BibtexError::CrossrefNotFound(
+            "child".into(),
+            "Parent".into()
+        )]
+    );
+    assert_eq!(resolved[0].get("title"), None);
+}
+
+#[test]
+fn test_bib_spans() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+
+    let comment_span = bibtex.comment_spans()[0];
+    assert_eq!((comment_span.start.line, comment_span.start.column), (1, 1));
+    assert_eq!(
+        &bib_str[comment_span.start.offset..comment_span.start.offset + 9],
+        "@Comment{"
+    );
+
+    let (key_span, value_span) = bibtex.variable_spans()[4];
+    assert_eq!(&bib_str[key_span.start.offset..key_span.end.offset], "ae");
+    assert_eq!(
+        &bib_str[value_span.start.offset..value_span.end.offset],
+        "alb # \" \" # ein"
+    );
+    assert_eq!((value_span.start.line, value_span.start.column), (13, 14));
+
+    let b0 = &bibtex.bibliographies()[0];
+    assert_eq!(b0.span().start.line, 15);
+    assert_eq!(b0.span().end.line, 25);
+    assert!(bib_str[b0.span().start.offset..b0.span().end.offset].starts_with("@article{einstein,"));
+    assert_eq!(
+        (
+            b0.citation_key_span().start.line,
+            b0.citation_key_span().start.column
+        ),
+        (15, 10)
+    );
+
+    let (key_span, value_span) = b0.tag_spans()[4];
+    assert_eq!((key_span.start.line, key_span.start.column), (21, 5));
+    assert_eq!(
+        &bib_str[value_span.start.offset..value_span.end.offset],
+        "10"
+    );
+    assert_eq!(value_span.start.column, 20);
+}
+
+#[test]
+fn test_bib_parsing_errors() {
+    let bib_str = "@misc{ first, title = {First} }
+
+@book{second,
+    author = \"Someone\",
+    title  \"Missing equal\",
+}";
+    match Bibtex::parse(bib_str) {
+        Err(BibtexError::Parsing(err)) => {
+            assert_eq!((err.line(), err.column()), (5, 12));
+            assert_eq!(err.offset(), 82);
+            assert_eq!(err.expected(), Expected::Equals);
+            assert_eq!(err.key(), Some("second"));
+            assert_eq!(err.snippet(), "    title  \"Missing equal\",");
+            assert_eq!(err.context(), &["a field", "a bibliography entry"][..]);
+            assert!(err
+                .to_string()
+                .ends_with("= note: while parsing a field, in a bibliography entry"));
+        }
+        other => panic!("Expected a parsing error, got {:?}", other),
+    }
+
+    match Bibtex::parse("@misc{ key, title = {Unclosed}") {
+        Err(BibtexError::Parsing(err)) => {
+            assert_eq!(err.expected(), Expected::ClosingBrace);
+            assert_eq!(err.column(), 31);
+        }
+        other => panic!("Expected a parsing error, got {:?}", other),
+    }
+
+    // The `@` in a value doesn't start another entry.
+    let err = parsing_error("@misc{key, author = {a@b.c}, title {A}}");
+    assert_eq!(err.key(), Some("key"));
+    let err = parsing_error("@misc{doi:10.1000/a=b(c), title {A}}");
+    assert_eq!(err.key(), Some("doi:10.1000/a=b(c)"));
+    let err = parsing_error("@string{ name = {a@b.c} # }");
+    assert_eq!(err.key(), Some("name"));
+}
+
+#[test]
+fn test_bib_lenient() {
+    let bib_str = "@string{ known = \"Known\" }
+@misc{ first, title = {First} }
+@misc{ broken, title = {Broken}, author = , }
+@misc{ unknown, title = unknown }
+@misc{ last, title = known }
+@misc{ unclosed, title = \"Unclosed\" ";
+
+    let (bibtex, errors) = Bibtex::parse_lenient(bib_str);
+
+    let keys = bibtex
+        .bibliographies()
+        .iter()
+        .map(|b| b.citation_key())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec!["first", "last"]);
+    assert_eq!(bibtex.bibliographies()[1].tags()[0].1, "Known");
+
+    assert_eq!(errors.len(), 3);
+    match errors[0] {
+        BibtexError::Parsing(ref err) => {
+            assert_eq!((err.line(), err.key()), (3, Some("broken")));
+            assert_eq!(err.expected(), Expected::Value);
+        }
+        ref other => panic!("Expected a parsing error, got {:?}", other),
+    }
+    match errors[1] {
+        BibtexError::Parsing(ref err) => {
+            assert_eq!((err.line(), err.key()), (6, Some("unclosed")));
+        }
+        ref other => panic!("Expected a parsing error, got {:?}", other),
+    }
+    assert_eq!(
+        errors[2],
+        BibtexError::StringVariableNotFound("unknown".into())
+    );
+
+    assert!(Bibtex::parse(bib_str).is_err());
+}
+
+fn parsing_error(bib_str: &str) -> ParseError {
+    match Bibtex::parse(bib_str) {
+        Err(BibtexError::Parsing(err)) => err,
+        other => panic!(
+            "Expected a parsing error for {:?}, got {:?}",
+            bib_str, other
+        ),
+    }
+}
+
+// Regression tests for inputs which used to panic.
+#[test]
+fn test_bib_malformed_inputs() {
+    // An `@` without an entry type is a part of a comment.
+    assert_eq!(Bibtex::parse("@{").unwrap().comments(), &vec!["@{"]);
+    assert_eq!(Bibtex::parse("@").unwrap().comments(), &vec!["@"]);
+
+    let err = parsing_error("@misc{key, title = {Unterminated");
+    assert_eq!(
+        (err.expected(), err.column()),
+        (Expected::MatchingBrace, 20)
+    );
+    assert_eq!(err.key(), Some("key"));
+    let err = parsing_error("@misc{key, title = \"Unterminated");
+    assert_eq!(
+        (err.expected(), err.column()),
+        (Expected::MatchingQuote, 20)
+    );
+    let err = parsing_error("@misc{key, title = \"A\" # \"B");
+    assert_eq!(
+        (err.expected(), err.column()),
+        (Expected::MatchingQuote, 26)
+    );
+    let err = parsing_error("@comment{Unterminated");
+    assert_eq!((err.expected(), err.column()), (Expected::MatchingBrace, 9));
+    let err = parsing_error("@misc{key, title = ");
+    assert_eq!(err.expected(), Expected::Value);
+    let err = parsing_error("@string{ key = ");
+    assert_eq!(err.expected(), Expected::Value);
+
+    assert_eq!(
+        Bibtex::parse("@string{ a = b } @string{ b = a # \"!\" }"),
+        Err(BibtexError::CyclicStringVariable("a".into()))
+    );
+    assert_eq!(
+        Bibtex::parse("@string{ a = a }"),
+        Err(BibtexError::CyclicStringVariable("a".into()))
+    );
+}
+
+#[test]
+fn test_bib_truncated_inputs() {
+    let bib_str = read_file("samples/test.bib");
+
+    for end in (0..bib_str.len()).filter(|&i| bib_str.is_char_boundary(i)) {
+        let truncated = &bib_str[..end];
+        if let Err(err) = Bibtex::parse(truncated) {
+            assert!(!err.to_string().is_empty());
+        }
+        Bibtex::parse_lenient(truncated);
+    }
+}
+
+#[test]
+fn test_bib_exported_identifiers() {
+    // Snippets of files exported by Zotero, JabRef, BibDesk and Mendeley.
+    let bib_str = "@string{ jan2020 = \"January 2020\" }
+@string{ conf:icml = \"International Conference on Machine Learning\" }
+
+@article{smith_neural_2020,
+    title = {Neural {Networks}},
+    volume = {12},
+    issn = {1234-5678},
+    doi = {10.1000/xyz123},
+    urldate = {2020-02-03},
+    journal = {Journal of Tests},
+    author = {Smith, John},
+    year = {2020},
+    note = jan2020,
+}
+
+@Article{Smith2020,
+  author       = {John Smith},
+  journaltitle = {Journal of Tests},
+  date         = {2020},
+  owner        = {jsmith},
+  timestamp    = {2020.02.03},
+}
+
+@inproceedings{Smith:2020aa,
+    booktitle = conf:icml,
+    date-added = {2020-02-03 10:00:00 +0100},
+    date-modified = {2020-02-04 11:00:00 +0100},
+    bdsk-url-1 = {https://doi.org/10.1000/xyz123}}
+
+@article{Smith2020a,
+    archivePrefix = {arXiv},
+    arxivId = {2002.00001},
+    isbn_13 = {978-3-16-148410-0},
+    mendeley-groups = {Thesis},
+    mendeley-tags = {deep learning,tests}
+}
+";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let biblios = bibtex.bibliographies();
+
+    assert_eq!(biblios[0].tags()[8], ("note".into(), "January 2020".into()));
+    assert_eq!(
+        biblios[1].tags()[4],
+        ("timestamp".into(), "2020.02.03".into())
+    );
+    assert_eq!(
+        biblios[2].tags()[0],
+        (
+            "booktitle".into(),
+            "International Conference on Machine Learning".into()
+        )
+    );
+    assert_eq!(
+        biblios[2].tags()[3],
+        ("bdsk-url-1".into(), "https://doi.org/10.1000/xyz123".into())
+    );
+    assert_eq!(
+        biblios[3].tags()[2],
+        ("isbn_13".into(), "978-3-16-148410-0".into())
+    );
+    assert_eq!(
+        biblios[3].tags()[4],
+        ("mendeley-tags".into(), "deep learning,tests".into())
+    );
+}
@@ -0,0 +1,195 @@
+#![cfg(feature = "csl")]
+
+extern crate nom_bibtex;
+#[macro_use]
+extern crate serde_json;
+
+use nom_bibtex::csl::{from_csl, from_csl_json, to_csl, to_csl_json, CslError};
+use nom_bibtex::Bibtex;
+
+#[test]
+fn test_csl_types() {
+    let types = [
+        ("article", "article-journal", "article"),
+        ("book", "book", "book"),
+        ("booklet", "pamphlet", "booklet"),
+        ("inbook", "chapter", "incollection"),
+        ("incollection", "chapter", "incollection"),
+        ("inproceedings", "paper-conference", "inproceedings"),
+        ("conference", "paper-conference", "inproceedings"),
+        ("manual", "report", "techreport"),
+        ("mastersthesis", "thesis", "mastersthesis"),
+        ("misc", "document", "misc"),
+        ("online", "webpage", "misc"),
+        ("phdthesis", "thesis", "phdthesis"),
+        ("proceedings", "book", "book"),
+        ("techreport", "report", "techreport"),
+        ("unpublished", "manuscript", "unpublished"),
+        ("unknown", "document", "misc"),
+    ];
+    for &(entry_type, csl_type, imported) in &types {
+        let bib_str = format!("@{}{{key, title = {{A title}}}}", entry_type);
+        let bibtex = Bibtex::parse(&bib_str).unwrap();
+        let item = to_csl(&bibtex.bibliographies()[0]);
+        assert_eq!(item["type"], csl_type, "{}", entry_type);
+        assert_eq!(
+            from_csl(&item).unwrap().entry_type(),
+            imported,
+            "{}",
+            csl_type
+        );
+    }
+}
+
+#[test]
+fn test_csl_export() {
+    let bib_str = "@article{einstein,
+    author = {Einstein, A. and de Broglie, Jr, Louis and {The Royal Society} and others},
+    title = {Zur Elektrodynamik bewegter K{\\\"o}rper},
+    journal = {Annalen der Physik},
+    volume = 17,
+    number = 10,
+    pages = {891--921},
+    year = 1905,
+    month = jun,
+    doi = {10.1002/andp.19053221004},
+    urldate = {2018-03-21},
+    crossref = {ignored}
+}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let item = to_csl(&bibtex.bibliographies()[0]);
+    assert_eq!(
+        item,
+        json!({
+            "id": "einstein",
+            "type": "article-journal",
+            "author": [
+                { "family": "Einstein", "given": "A." },
+                { "family": "Broglie", "given": "Louis", "non-dropping-particle": "de", "suffix": "Jr" },
+                { "literal": "The Royal Society" }
+            ],
+            "title": "Zur Elektrodynamik bewegter Körper",
+            "container-title": "Annalen der Physik",
+            "volume": "17",
+            "issue": "10",
+            "page": "891-921",
+            "issued": { "date-parts": [[1905, 6]] },
+            "accessed": { "date-parts": [[2018, 3, 21]] },
+            "DOI": "10.1002/andp.19053221004"
+        })
+    );
+
+    let bib_str = "@phdthesis{thesis, school = {MIT}, number = 3, date = {2001-09}}
+@misc{misc, year = {circa 1900}}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let items = bibtex
+        .bibliographies()
+        .iter()
+        .map(to_csl)
+        .collect::<Vec<_>>();
+    assert_eq!(items[0]["publisher"], "MIT");
+    assert_eq!(items[0]["number"], "3");
+    assert_eq!(items[0]["issued"], json!({ "date-parts": [[2001, 9]] }));
+    assert_eq!(items[1]["issued"], json!({ "literal": "circa 1900" }));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&to_csl_json(bibtex.bibliographies())).unwrap();
+    assert_eq!(json, serde_json::Value::Array(items));
+}
+
+#[test]
+fn test_csl_import() {
+    let json = r#"[
+        {
+            "id": "smith",
+            "type": "chapter",
+            "author": [{ "family": "Smith", "given": "Jane", "non-dropping-particle": "van" }],
+            "editor": [{ "literal": "AT&T" }],
+            "title": "50% of Ångström",
+            "container-title": "Collected Works",
+            "publisher": "Springer",
+            "publisher-place": "Berlin",
+            "page": "12-34",
+            "volume": 2,
+            "issued": { "date-parts": [["2010", "4"]] },
+            "accessed": { "date-parts": [[2018, 3, 1]] },
+            "URL": "http://example.com/~smith/a_b%20c"
+        },
+        {
+            "id": "thesis",
+            "type": "thesis",
+            "genre": "Master's thesis",
+            "publisher": "MIT"
+        }
+    ]"#;
+    let bibliographies = from_csl_json(json).unwrap();
+    assert_eq!(bibliographies.len(), 2);
+
+    let chapter = &bibliographies[0];
+    assert_eq!(chapter.entry_type(), "incollection");
+    assert_eq!(chapter.citation_key(), "smith");
+    assert_eq!(
+        chapter.tags(),
+        &[
+            ("author".into(), "van Smith, Jane".into()),
+            ("editor".into(), "{AT\\&T}".into()),
+            ("year".into(), "2010".into()),
+            ("month".into(), "April".into()),
+            ("urldate".into(), "2018-03-01".into()),
+            ("title".into(), "50\\% of Ångström".into()),
+            ("address".into(), "Berlin".into()),
+            ("volume".into(), "2".into()),
+            ("url".into(), "http://example.com/~smith/a_b%20c".into()),
+            ("booktitle".into(), "Collected Works".into()),
+            ("publisher".into(), "Springer".into()),
+            ("pages".into(), "12--34".into()),
+        ][..]
+    );
+
+    let thesis = &bibliographies[1];
+    assert_eq!(thesis.entry_type(), "mastersthesis");
+    assert_eq!(thesis.get("school"), Some("MIT"));
+    assert_eq!(thesis.get("type"), None);
+
+    let single = from_csl_json(r#"{ "id": "key", "type": "webpage" }"#).unwrap();
+    assert_eq!(single[0].entry_type(), "misc");
+
+    match from_csl_json(r#"[{ "type": "book" }]"#) {
+        Err(CslError::InvalidItem(_)) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    match from_csl_json(r#"[{ "id": "a,b", "type": "book" }]"#) {
+        Err(CslError::InvalidItem(_)) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    match from_csl_json("[") {
+        Err(CslError::Json(_)) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_csl_round_trip() {
+    let bib_str = "@inproceedings{key,
+    author = {Erd{\\H o}s, Paul and van der Waals, Johannes},
+    title = {On the 50\\% rule},
+    booktitle = {Proceedings},
+    publisher = {ACM},
+    year = 1950,
+    pages = {1--10}
+}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let item = to_csl(&bibtex.bibliographies()[0]);
+    let imported = from_csl(&item).unwrap();
+    assert_eq!(imported.entry_type(), "inproceedings");
+    assert_eq!(
+        imported.get("author"),
+        Some("Erdős, Paul and van der Waals, Johannes")
+    );
+    assert_eq!(imported.get("title"), Some("On the 50\\% rule"));
+    assert_eq!(imported.get("booktitle"), Some("Proceedings"));
+    assert_eq!(imported.get("publisher"), Some("ACM"));
+    assert_eq!(imported.get("year"), Some("1950"));
+    assert_eq!(imported.get("pages"), Some("1--10"));
+    assert_eq!(to_csl(&imported), item);
+}
@@ -0,0 +1,186 @@
+extern crate nom_bibtex;
+
+use nom_bibtex::cst::{Body, Delimiters, Document, Item, Piece};
+use nom_bibtex::model::CommentStyle;
+use nom_bibtex::Bibtex;
+use std::fs::File;
+use std::io::prelude::*;
+
+fn read_file(filename: &str) -> String {
+    let mut file = File::open(filename).unwrap();
+    let mut bib_content = String::new();
+
+    file.read_to_string(&mut bib_content).unwrap();
+    bib_content
+}
+
+#[test]
+fn test_cst_lossless() {
+    let bib_str = read_file("samples/test.bib");
+    let document = Document::parse(&bib_str).unwrap();
+    assert_eq!(document.to_string(), bib_str);
+
+    let keys = document
+        .entries()
+        .filter_map(|entry| entry.citation_key())
+        .collect::<Vec<_>>();
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+    let expected_keys = bibtex
+        .bibliographies()
+        .iter()
+        .map(|bib| bib.citation_key())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, expected_keys);
+}
+
+#[test]
+fn test_cst_lossless_prefixes() {
+    let bib_str = read_file("samples/test.bib");
+    for (end, _) in bib_str.char_indices() {
+        let input = &bib_str[..end];
+        if let Ok(document) = Document::parse(input) {
+            assert_eq!(document.to_string(), input);
+        }
+    }
+}
+
+#[test]
+fn test_cst_minimal_diff() {
+    let bib_str = read_file("samples/test.bib");
+    let mut document = Document::parse(&bib_str).unwrap();
+    document
+        .entry_mut("einstein")
+        .unwrap()
+        .set_field("year", Piece::Braced("1906".into()).into());
+    let edited = document.to_string();
+
+    let changed = bib_str
+        .lines()
+        .zip(edited.lines())
+        .filter(|&(before, after)| before != after)
+        .collect::<Vec<_>>();
+    assert_eq!(bib_str.lines().count(), edited.lines().count());
+    assert_eq!(changed.len(), 1);
+    assert!(changed[0].1.contains("{1906}"));
+
+    let bibtex = Bibtex::parse(&edited).unwrap();
+    let year = bibtex.bibliographies()[0]
+        .tags()
+        .iter()
+        .find(|tag| tag.0 == "year")
+        .cloned();
+    assert_eq!(year, Some(("year".into(), "1906".into())));
+}
+
+#[test]
+fn test_cst_errors() {
+    for input in &[
+        "@misc{key, title {Foo}}",
+        "@misc{key, title = {Foo}",
+        "@misc(key, title = {Foo}}",
+    ] {
+        assert!(Document::parse(input).is_err());
+        assert_eq!(Document::parse(input).err(), Bibtex::parse(input).err());
+    }
+
+    // An `@` without an entry type is kept as text.
+    for input in &[
+        "@",
+        "Write to @john\n@misc{k, title={A}}",
+        "@misc{k}\njunk @ here",
+    ] {
+        assert_eq!(Document::parse(input).unwrap().to_string(), *input);
+    }
+}
+
+#[test]
+fn test_cst_values() {
+    let bib_str = "@string{ name = {Braced} # 2 # \"quoted\" }
+@preamble{ {a} # name }
+@preamble{ raw {nested} text }
+@misc{ key, title = 1 # {a} # \"b\" # name }";
+    let document = Document::parse(bib_str).unwrap();
+    assert_eq!(document.to_string(), bib_str);
+
+    let values = document
+        .entries()
+        .map(|entry| match entry.body {
+            Body::String { ref field, .. } => field.value.to_string(),
+            Body::Preamble { ref value, .. } => value.to_string(),
+            Body::Bibliography { ref fields, .. } => fields[0].value.to_string(),
+            Body::Comment(ref comment) => comment.to_string(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            "{Braced} # 2 # \"quoted\"",
+            "{a} # name",
+            "raw {nested} text",
+            "1 # {a} # \"b\" # name",
+        ]
+    );
+}
+
+#[test]
+fn test_cst_parentheses() {
+    let bib_str =
+        "@comment( {(nested)} )\n@preamble( raw {)} text )\n@misc( key, title = {A (title)} )";
+    let document = Document::parse(bib_str).unwrap();
+    assert_eq!(document.to_string(), bib_str);
+
+    let entries = document.entries().collect::<Vec<_>>();
+    assert_eq!(entries.len(), 3);
+    assert!(entries
+        .iter()
+        .all(|entry| entry.delimiters == Delimiters::Parentheses));
+    assert_eq!(entries[0].body, Body::Comment(" {(nested)} ".into()));
+    assert_eq!(entries[1].to_string(), "@preamble( raw {)} text )");
+    assert_eq!(
+        entries[2].field("title").unwrap().value.pieces[0].1,
+        Piece::Braced("A (title)".into())
+    );
+}
+
+#[test]
+fn test_cst_citation_keys() {
+    let bib_str = "@misc{ empty }\n@misc{doi:10.1000/a+b , title = {A}}";
+    let mut document = Document::parse(bib_str).unwrap();
+    assert_eq!(document.to_string(), bib_str);
+    assert!(document.entry("doi:10.1000/a+b").is_some());
+
+    document
+        .entry_mut("empty")
+        .unwrap()
+        .set_field("title", Piece::Braced("A title".into()).into());
+    assert_eq!(
+        document.to_string(),
+        "@misc{ empty,\n    title = {A title}\n}\n@misc{doi:10.1000/a+b , title = {A}}"
+    );
+}
+
+#[test]
+fn test_cst_comment_styles() {
+    let bib_str =
+        "@comment{ Disabled:\n@misc{ disabled, title = {A} }\n}\n@misc{ key, title = {B} }";
+
+    let document = Document::parse(bib_str).unwrap();
+    assert_eq!(document.to_string(), bib_str);
+    let keys = document
+        .entries()
+        .filter_map(|entry| entry.citation_key())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec!["key"]);
+
+    let document = Document::parse_with(bib_str, CommentStyle::Bibtex).unwrap();
+    assert_eq!(document.to_string(), bib_str);
+    let keys = document
+        .entries()
+        .filter_map(|entry| entry.citation_key())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec!["disabled", "key"]);
+    assert_eq!(
+        document.items()[0],
+        Item::Text("@comment{ Disabled:\n".into())
+    );
+}
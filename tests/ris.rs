@@ -0,0 +1,205 @@
+extern crate nom_bibtex;
+
+use nom_bibtex::ris::{self, RisError};
+use nom_bibtex::Bibtex;
+
+#[test]
+fn test_ris_read() {
+    let ris_str = "\u{feff}TY  - JOUR
+AU  - Smith, Jane
+AU  - King, Martin Luther, Jr.
+TI  - Heat & light:
+      50% of the story
+JO  - Journal of Physics
+PY  - 2018/03/21/
+VL  - 12
+IS  - 3
+SP  - 100
+EP  - 110
+DO  - 10.1000/a_b
+UR  - http://example.com/~smith
+KW  - heat
+KW  - light
+SN  - 1234-5678
+ER  - 
+
+TY  - CHAP
+A1  - Smith, Jane
+T1  - A chapter
+T2  - Collected Works
+ED  - Doe, John
+PY  - 2018
+PB  - Springer
+SN  - 978-3-16-148410-0
+SP  - 5-9
+ER  - 
+
+TY  - THES
+ID  - MyThesis
+TI  - A thesis
+PB  - MIT
+ER  - 
+
+TY  - WEB
+TI  - A page
+ER  -
+";
+    let bibliographies = ris::read(ris_str).unwrap();
+    assert_eq!(bibliographies.len(), 4);
+
+    let article = &bibliographies[0];
+    assert_eq!(article.entry_type(), "article");
+    assert_eq!(article.citation_key(), "smith2018");
+    assert_eq!(
+        article.tags(),
+        &[
+            (
+                "author".into(),
+                "Smith, Jane and King, Jr., Martin Luther".into()
+            ),
+            ("title".into(), "Heat \\& light: 50\\% of the story".into()),
+            ("journal".into(), "Journal of Physics".into()),
+            ("year".into(), "2018".into()),
+            ("month".into(), "March".into()),
+            ("volume".into(), "12".into()),
+            ("number".into(), "3".into()),
+            ("doi".into(), "10.1000/a_b".into()),
+            ("url".into(), "http://example.com/~smith".into()),
+            ("pages".into(), "100--110".into()),
+            ("issn".into(), "1234-5678".into()),
+            ("keywords".into(), "heat, light".into()),
+        ][..]
+    );
+
+    let chapter = &bibliographies[1];
+    assert_eq!(chapter.entry_type(), "incollection");
+    assert_eq!(chapter.citation_key(), "smith2018a");
+    assert_eq!(chapter.get("author"), Some("Smith, Jane"));
+    assert_eq!(chapter.get("editor"), Some("Doe, John"));
+    assert_eq!(chapter.get("booktitle"), Some("Collected Works"));
+    assert_eq!(chapter.get("publisher"), Some("Springer"));
+    assert_eq!(chapter.get("isbn"), Some("978-3-16-148410-0"));
+    assert_eq!(chapter.get("pages"), Some("5--9"));
+
+    let thesis = &bibliographies[2];
+    assert_eq!(thesis.entry_type(), "phdthesis");
+    assert_eq!(thesis.citation_key(), "MyThesis");
+    assert_eq!(thesis.get("school"), Some("MIT"));
+
+    assert_eq!(bibliographies[3].entry_type(), "misc");
+    assert_eq!(bibliographies[3].citation_key(), "ris");
+}
+
+#[test]
+fn test_ris_errors() {
+    assert_eq!(
+        ris::read("TY  - JOUR\nTI  - A title\n"),
+        Err(RisError::UnterminatedRecord(1))
+    );
+    assert_eq!(
+        ris::read("\nAU  - Smith, Jane\n"),
+        Err(RisError::TagOutsideRecord(2, "AU".into()))
+    );
+    assert_eq!(
+        ris::read("TY  - JOUR\nER  - \nSmith\n"),
+        Err(RisError::InvalidLine(3))
+    );
+    assert_eq!(
+        ris::read("TY  - JOUR\n  continued\nER  - \n"),
+        Err(RisError::InvalidLine(2))
+    );
+    assert_eq!(
+        ris::read("\nTY  - JOUR\nID  - smith 2018\nER  - \n"),
+        Err(RisError::InvalidKey(2, "smith 2018".into()))
+    );
+    assert_eq!(
+        RisError::TagOutsideRecord(2, "AU".into()).to_string(),
+        "RIS tag outside of a record.: `AU` at line 2"
+    );
+    assert_eq!(ris::read("\n\n"), Ok(vec![]));
+}
+
+#[test]
+fn test_ris_write() {
+    let bib_str = "@string{jp = \"Journal of Physics\"}
+@article{key,
+    author = {G{\\\"o}del, Kurt and van Beethoven, Jr, Ludwig and others},
+    title = {On {\\TeX} \\& more},
+    journal = jp,
+    year = 2018,
+    month = mar,
+    volume = 12,
+    pages = {100--110},
+    doi = {10.1000/a_b},
+    keywords = {heat; light},
+    crossref = {ignored}
+}
+@phdthesis{thesis, school = {MIT}, date = {2001-09-01}}
+@software{tool, title = {A tool}}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    assert_eq!(
+        ris::write(bibtex.bibliographies()),
+        "TY  - JOUR
+ID  - key
+AU  - Gödel, Kurt
+AU  - van Beethoven, Ludwig, Jr
+TI  - On TeX & more
+JO  - Journal of Physics
+PY  - 2018
+DA  - 2018/03//
+VL  - 12
+DO  - 10.1000/a_b
+SP  - 100
+EP  - 110
+KW  - heat
+KW  - light
+ER  - 
+
+TY  - THES
+ID  - thesis
+PY  - 2001
+DA  - 2001/09/01
+PB  - MIT
+ER  - 
+
+TY  - GEN
+ID  - tool
+TI  - A tool
+ER  - 
+"
+    );
+}
+
+#[test]
+fn test_ris_round_trip() {
+    let bib_str = "@inproceedings{key,
+    author = {Erd{\\H o}s, Paul and Smith, Jane},
+    title = {On the 50\\% rule},
+    booktitle = {Proceedings},
+    year = 1950,
+    month = {June},
+    pages = {1--10},
+    publisher = {ACM},
+    address = {New York},
+    url = {http://example.com/a_b}
+}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+    let bibliographies = ris::read(&ris::write(bibtex.bibliographies())).unwrap();
+    assert_eq!(bibliographies.len(), 1);
+    let imported = &bibliographies[0];
+    assert_eq!(imported.entry_type(), "inproceedings");
+    assert_eq!(imported.citation_key(), "key");
+    assert_eq!(imported.get("author"), Some("Erdős, Paul and Smith, Jane"));
+    for &field in &[
+        "title",
+        "booktitle",
+        "year",
+        "month",
+        "pages",
+        "publisher",
+        "address",
+        "url",
+    ] {
+        assert_eq!(imported.get(field), bibtex.bibliographies()[0].get(field));
+    }
+}
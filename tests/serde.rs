@@ -0,0 +1,158 @@
+#![cfg(feature = "serde")]
+
+extern crate nom_bibtex;
+extern crate serde;
+extern crate serde_json;
+
+use nom_bibtex::model::{KeyValue, StringValueType};
+use nom_bibtex::names::Name;
+use nom_bibtex::writer::{WriteError, Writer};
+use nom_bibtex::{Bibliography, Bibtex};
+use serde::Deserialize;
+
+#[test]
+fn test_round_trip() {
+    let bib_str = "@comment{A comment}
+@preamble{ \"A preamble\" }
+@string{ name = \"Me\" }
+@article{ key, author = name, title = {A \\emph{title}}, year = 2018 }";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+
+    let json = serde_json::to_string(&bibtex).unwrap();
+    assert_eq!(
+        json,
+        r#"{"comments":["A comment"],"preambles":["A preamble"],"variables":[["name","Me"]],"bibliographies":[{"entry_type":"article","citation_key":"key","tags":[["author","Me"],["title","A \\emph{title}"],["year","2018"]]}]}"#
+    );
+    let deserialized: Bibtex = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, bibtex);
+    assert_eq!(deserialized.get("KEY"), Some(&bibtex.bibliographies()[0]));
+
+    let json = serde_json::to_string(&bibtex.bibliographies()[0]).unwrap();
+    let deserialized: Bibliography = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, bibtex.bibliographies()[0]);
+    assert_eq!(deserialized.get("Title"), Some("A \\emph{title}"));
+}
+
+#[test]
+fn test_round_trip_escaped_comment() {
+    let bib_str = "@comment{A \"multi-line\"
+comment}
+@misc{ key, title = {A} }";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+
+    let json = serde_json::to_string(&bibtex).unwrap();
+    assert!(json.contains(r#""comments":["A \"multi-line\"\ncomment"]"#));
+    let deserialized: Bibtex = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, bibtex);
+    assert_eq!(deserialized.comments(), &vec!["A \"multi-line\"\ncomment"]);
+}
+
+#[test]
+fn test_parsing_model() {
+    let value = vec![
+        StringValueType::Abbreviation("name"),
+        StringValueType::Number("1"),
+    ];
+    let json = serde_json::to_string(&KeyValue::new("author", value.clone())).unwrap();
+    assert_eq!(
+        json,
+        r#"{"key":"author","value":[{"Abbreviation":"name"},{"Number":"1"}],"value_source":""}"#
+    );
+    let key_value: KeyValue = serde_json::from_str(&json).unwrap();
+    assert_eq!(key_value.value, value);
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Article,
+    Book,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Paper {
+    entry_type: Kind,
+    citation_key: String,
+    title: String,
+    year: u32,
+    author: Vec<Name>,
+    editor: Option<Vec<String>>,
+    keywords: Option<Vec<String>>,
+    doi: Option<String>,
+}
+
+#[test]
+fn test_deserialize_struct() {
+    let bibtex = Bibtex::parse(
+        "@book{ knuth,
+    Author = {Knuth, Donald and Leslie Lamport},
+    editor = {Me and You},
+    keywords = {algorithms, sorting; searching and hashing},
+    title = {The Art},
+    year = {1968},
+}
+@book{ bad, author = {Me}, title = {Bad}, year = {soon} }",
+    )
+    .unwrap();
+
+    let paper = Paper::deserialize(&bibtex.bibliographies()[0]).unwrap();
+    assert_eq!(
+        paper,
+        Paper {
+            entry_type: Kind::Book,
+            citation_key: "knuth".into(),
+            title: "The Art".into(),
+            year: 1968,
+            author: vec![
+                Name::new("Donald", "", "Knuth", ""),
+                Name::new("Leslie", "", "Lamport", ""),
+            ],
+            editor: Some(vec!["Me".into(), "You".into()]),
+            keywords: Some(vec![
+                "algorithms".into(),
+                "sorting".into(),
+                "searching and hashing".into(),
+            ]),
+            doi: None,
+        }
+    );
+
+    let err = Paper::deserialize(&bibtex.bibliographies()[1]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value: string \"soon\", expected u32"
+    );
+}
+
+#[test]
+fn test_deserialize_sequences() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Sequences {
+        translator: Vec<String>,
+        pages: Vec<u32>,
+        note: Vec<String>,
+    }
+
+    let bibtex =
+        Bibtex::parse("@misc{key, translator = {A, B and C}, pages = {12; 15,}, note = {A and B}}")
+            .unwrap();
+    assert_eq!(
+        Sequences::deserialize(&bibtex.bibliographies()[0]).unwrap(),
+        Sequences {
+            translator: vec!["A, B".into(), "C".into()],
+            pages: vec![12, 15],
+            note: vec!["A and B".into()],
+        }
+    );
+}
+
+#[test]
+fn test_write_deserialized_variables() {
+    let json =
+        r#"{"comments":[],"preambles":[],"variables":[["my name","Me"]],"bibliographies":[]}"#;
+    let bibtex: Bibtex = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        Writer::new().write(&bibtex),
+        Err(WriteError::InvalidVariableName("my name".into()))
+    );
+}
@@ -0,0 +1,266 @@
+extern crate nom_bibtex;
+
+use nom_bibtex::latex::{decode, Encoding};
+use nom_bibtex::model::{Bibliography, Delimiters};
+use nom_bibtex::writer::{Delimiter, FieldOrder, WriteError, Writer};
+use nom_bibtex::Bibtex;
+use std::fs::File;
+use std::io::prelude::*;
+
+fn read_file(filename: &str) -> String {
+    let mut file = File::open(filename).unwrap();
+    let mut bib_content = String::new();
+
+    file.read_to_string(&mut bib_content).unwrap();
+    bib_content
+}
+
+fn writers() -> Vec<Writer> {
+    vec![
+        Writer::new(),
+        Writer::new()
+            .indent("\t")
+            .align_fields(false)
+            .delimiter(Delimiter::Quotes)
+            .trailing_comma(false),
+    ]
+}
+
+fn reordering_writers() -> Vec<Writer> {
+    vec![
+        Writer::new().field_order(FieldOrder::Alphabetical),
+        Writer::new().field_order(FieldOrder::Custom(vec!["title".into(), "author".into()])),
+    ]
+}
+
+fn sorted_tags(bibtex: &Bibtex) -> Vec<Vec<(String, String)>> {
+    bibtex
+        .bibliographies()
+        .iter()
+        .map(|bib| {
+            let mut tags = bib.tags().clone();
+            tags.sort();
+            tags
+        })
+        .collect()
+}
+
+#[test]
+fn test_round_trip() {
+    let bib_str = read_file("samples/test.bib");
+    let bibtex = Bibtex::parse(&bib_str).unwrap();
+
+    for writer in writers() {
+        let written = writer.write(&bibtex).unwrap();
+        let parsed = Bibtex::parse(&written).unwrap();
+        assert_eq!(parsed, bibtex, "Written:\n{}", written);
+
+        // Writing again gives exactly the same output.
+        assert_eq!(writer.write(&parsed).unwrap(), written);
+    }
+
+    for writer in reordering_writers() {
+        let written = writer.write(&bibtex).unwrap();
+        let parsed = Bibtex::parse(&written).unwrap();
+        assert_eq!(parsed.variables(), bibtex.variables());
+        assert_eq!(sorted_tags(&parsed), sorted_tags(&bibtex));
+        assert_eq!(writer.write(&parsed).unwrap(), written);
+    }
+}
+
+#[test]
+fn test_round_trip_special_values() {
+    let bib_str = "Not an entry but a comment with a {brace}
+
+@preamble{ \"\\newcommand{\\noopsort}[1]{}\" }
+@preamble{ {A \"quoted\" preamble} }
+@string{ mail = \"contact@example.com\" }
+@string{ quote = {A \"quoted\" string} }
+
+@misc{ key,
+    note = mail,
+    title = \" Spaces around \",
+    howpublished = \"{\"}Quoted{\"}\",
+    number = 42,
+}";
+    let bibtex = Bibtex::parse(bib_str).unwrap();
+
+    for writer in writers() {
+        let written = writer.write(&bibtex).unwrap();
+        assert_eq!(
+            Bibtex::parse(&written).unwrap(),
+            bibtex,
+            "Written:\n{}",
+            written
+        );
+    }
+}
+
+#[test]
+fn test_encoding() {
+    let bibliography = Bibliography::new(
+        "book",
+        "goedel",
+        vec![
+            ("author".into(), "Kurt Gödel".into()),
+            (
+                "title".into(),
+                "Über formal unentscheidbare Sätze & Co".into(),
+            ),
+        ],
+    );
+
+    let writer = Writer::new().encoding(Encoding::Ascii);
+    let written = writer.write_bibliography(&bibliography).unwrap();
+    assert_eq!(
+        written,
+        "@book{goedel,
+    author = {Kurt G{\\\"o}del},
+    title  = {{\\\"U}ber formal unentscheidbare S{\\\"a}tze \\& Co},
+}
+"
+    );
+
+    let bibtex = Bibtex::parse(&written).unwrap();
+    let tags = bibtex.bibliographies()[0].tags();
+    for (tag, expected) in tags.iter().zip(bibliography.tags()) {
+        assert_eq!(decode(&tag.1).text, expected.1);
+    }
+
+    // The encoded braces don't need to be balanced.
+    let braces = Bibliography::new("misc", "key", vec![("title".into(), "a { b".into())]);
+    let written = writer.write_bibliography(&braces).unwrap();
+    let bibtex = Bibtex::parse(&written).unwrap();
+    assert_eq!(
+        decode(bibtex.bibliographies()[0].title().unwrap()).text,
+        "a { b"
+    );
+
+    let writer = Writer::new().encoding(Encoding::Minimal);
+    assert!(writer
+        .write_bibliography(&bibliography)
+        .unwrap()
+        .contains("title  = {Über formal unentscheidbare Sätze \\& Co},"));
+}
+
+#[test]
+fn test_write_strings_and_preambles() {
+    let bibtex = Bibtex::parse(
+        "@preamble{ \"\\noop\" # {A \"quoted\" preamble} }
+@string{ name = \"Me\" }
+@string{ mail = {contact} # \"@example.com\" }",
+    )
+    .unwrap();
+    assert_eq!(
+        Writer::new().write(&bibtex).unwrap(),
+        "@preamble{{\\noopA \"quoted\" preamble}}
+
+@string{name = {Me}}
+
+@string{mail = {contact@example.com}}
+"
+    );
+    assert_eq!(
+        Writer::new()
+            .delimiter(Delimiter::Quotes)
+            .write(&bibtex)
+            .unwrap(),
+        "@preamble{{\\noopA \"quoted\" preamble}}
+
+@string{name = \"Me\"}
+
+@string{mail = \"contact@example.com\"}
+"
+    );
+}
+
+#[test]
+fn test_write_parentheses() {
+    let bibtex =
+        Bibtex::parse("@misc( key, title = {A (title)} )\n@misc{ other, year = 2018 }").unwrap();
+    let written = Writer::new().write(&bibtex).unwrap();
+    assert_eq!(
+        written,
+        "@misc(key,
+    title = {A (title)},
+)
+
+@misc{other,
+    year = {2018},
+}
+"
+    );
+    assert_eq!(Bibtex::parse(&written).unwrap(), bibtex);
+
+    let mut bibliography = bibtex.bibliographies()[1].clone();
+    bibliography.set_delimiters(Delimiters::Parentheses);
+    assert_eq!(
+        Writer::new().write_bibliography(&bibliography).unwrap(),
+        "@misc(other,\n    year = {2018},\n)\n"
+    );
+}
+
+#[test]
+fn test_write_errors() {
+    let bibliography = Bibliography::new(
+        "misc",
+        "key",
+        vec![("title".into(), " A \"quoted\" title ".into())],
+    );
+    for writer in writers() {
+        let written = writer.write_bibliography(&bibliography).unwrap();
+        let bibtex = Bibtex::parse(&written).unwrap();
+        assert_eq!(bibtex.bibliographies()[0], bibliography, "{}", written);
+    }
+
+    let bibliography = Bibliography::new(
+        "misc",
+        "key",
+        vec![("title".into(), "An } unbalanced title".into())],
+    );
+    assert_eq!(
+        Writer::new().write_bibliography(&bibliography),
+        Err(WriteError::UnbalancedBraces("An } unbalanced title".into()))
+    );
+    // An unbalanced value is an error, not a panic.
+    let bibliography = Bibliography::new("misc", "k", vec![("title".into(), "a } b".into())]);
+    assert!(Writer::new().write_bibliography(&bibliography).is_err());
+    assert!(Writer::new()
+        .write_bibliography_to(&bibliography, &mut String::new())
+        .is_err());
+
+    for &key in &["my key", "a,b", "a{b}", ""] {
+        let bibliography = Bibliography::new("misc", key, vec![]);
+        assert_eq!(
+            Writer::new().write_bibliography(&bibliography),
+            Err(WriteError::InvalidCitationKey(key.into()))
+        );
+    }
+    for &entry_type in &["online-2", "string", "Comment", "preamble", ""] {
+        let bibliography = Bibliography::new(entry_type, "key", vec![]);
+        assert_eq!(
+            Writer::new().write_bibliography(&bibliography),
+            Err(WriteError::InvalidEntryType(entry_type.into()))
+        );
+    }
+    for &name in &["my field", "2020", "a=b", ""] {
+        let bibliography = Bibliography::new("misc", "key", vec![(name.into(), "A".into())]);
+        assert_eq!(
+            Writer::new().write_bibliography(&bibliography),
+            Err(WriteError::InvalidTagName(name.into()))
+        );
+    }
+
+    // The comments are written before the entries, so the unbalanced ones
+    // would be merged once parsed back.
+    let bibtex = Bibtex::parse("a } b\n@misc{k,}\nc { d\n@misc{j,}\n").unwrap();
+    assert_eq!(
+        Writer::new().write(&bibtex),
+        Err(WriteError::UnbalancedComment("a } b".into()))
+    );
+
+    let mut bibliography = Bibliography::new("misc", "a)b", vec![]);
+    assert!(Writer::new().write_bibliography(&bibliography).is_ok());
+    bibliography.set_delimiters(Delimiters::Parentheses);
+    assert!(Writer::new().write_bibliography(&bibliography).is_err());
+}